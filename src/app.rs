@@ -1,67 +1,273 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
 use eframe::egui::{Color32, Direction, Id, Layout};
-use eframe::{egui, App, Frame};
+use eframe::{egui, App, CreationContext, Frame, Storage};
+use instant::Instant;
+use serde::{Deserialize, Serialize};
 use url::Url;
 
+use crate::dash_config::{self, DashConfigImporter};
+use crate::event;
+use crate::subscriptions::Subscriptions;
 use crate::workspace::Workspace;
 
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct RiemannDashApp {
     valid_url: Url,
     editable_url: String,
     selected_workspace: usize,
     workspaces: Vec<Workspace>,
+    /// The maximum number of frames per second, bounds the CPU used by dense dashboards.
+    max_fps: f32,
+    #[serde(skip)]
+    frames: Arc<FrameLimiter>,
+    #[serde(skip)]
+    subscriptions: Subscriptions,
+    #[serde(skip)]
+    dash_importer: Option<DashConfigImporter>,
+    /// Only shows the views of the selected workspace, without any menu.
+    #[serde(skip)]
+    kiosk: bool,
+}
+
+impl RiemannDashApp {
+    /// Restores the previous state of the dashboard from the eframe storage, if any.
+    pub fn new(cc: &CreationContext<'_>) -> Self {
+        let mut app = cc.storage.map(Self::restore).unwrap_or_default();
+
+        // repaint the dashboard as soon as new events arrive, within the frame rate
+        let ctx = cc.egui_ctx.clone();
+        let frames = app.frames.clone();
+        app.subscriptions.set_wakeup(move || frames.request_repaint(&ctx));
+
+        app
+    }
+
+    /// Reads the state written by [`App::save`], the state of the previous versions is RON.
+    fn restore(storage: &dyn Storage) -> Self {
+        let state = match storage.get_string(eframe::APP_KEY) {
+            Some(state) => state,
+            None => return Self::default(),
+        };
+        match serde_json::from_str(&state) {
+            Ok(app) => app,
+            Err(e) => eframe::get_value(storage, eframe::APP_KEY).unwrap_or_else(|| {
+                tracing::warn!("could not restore the dashboard: {}", e);
+                Self::default()
+            }),
+        }
+    }
+
+    pub fn set_url(&mut self, url: Url) {
+        self.editable_url = url.as_str().to_string();
+        self.valid_url = url;
+    }
+
+    /// Adds the workspace or replaces the one with the same name.
+    pub fn load_workspace(&mut self, workspace: Workspace) {
+        match self.workspaces.iter_mut().find(|w| w.name == workspace.name) {
+            Some(existing) => *existing = workspace,
+            None => self.workspaces.push(workspace),
+        }
+    }
+
+    /// Selects the first workspace with this name, returns `false` if there is none.
+    pub fn select_workspace(&mut self, name: &str) -> bool {
+        match self.workspaces.iter().position(|w| w.name == name) {
+            Some(i) => {
+                self.selected_workspace = i;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn set_kiosk(&mut self, kiosk: bool) {
+        self.kiosk = kiosk;
+    }
+
+    pub fn set_max_fps(&mut self, max_fps: f32) {
+        self.max_fps = max_fps.clamp(MIN_FPS, MAX_FPS);
+    }
+}
+
+/// Delays the repaints requested by the subscriptions to the end of the current frame,
+/// the events received in the meantime are shown together in the next one.
+#[derive(Default)]
+struct FrameLimiter {
+    frames: Mutex<Frames>,
+}
+
+#[derive(Default)]
+struct Frames {
+    last: Option<Instant>,
+    duration: Duration,
+    /// Whether a repaint is already scheduled at the end of the frame.
+    deferred: bool,
 }
 
+impl FrameLimiter {
+    fn frame_started(&self, max_fps: f32) {
+        let mut frames = self.frames.lock().unwrap();
+        frames.last = Some(Instant::now());
+        frames.duration = Duration::from_secs_f32(1.0 / max_fps.max(MIN_FPS));
+    }
+
+    fn request_repaint(self: &Arc<Self>, ctx: &egui::Context) {
+        match self.next_repaint() {
+            Repaint::Now => ctx.request_repaint(),
+            Repaint::After(remaining) => {
+                let limiter = self.clone();
+                let ctx = ctx.clone();
+                let repaint = move || {
+                    limiter.frames.lock().unwrap().deferred = false;
+                    ctx.request_repaint();
+                };
+                event::wakeup_after(remaining, Arc::new(repaint));
+            }
+            Repaint::Scheduled => (),
+        }
+    }
+
+    fn next_repaint(&self) -> Repaint {
+        let mut frames = self.frames.lock().unwrap();
+        if frames.deferred {
+            return Repaint::Scheduled;
+        }
+        let end = frames.last.map(|last| last + frames.duration);
+        match end.and_then(|end| end.checked_duration_since(Instant::now())) {
+            Some(remaining) => {
+                frames.deferred = true;
+                Repaint::After(remaining)
+            }
+            None => Repaint::Now,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Repaint {
+    Now,
+    /// At the end of the current frame.
+    After(Duration),
+    /// A repaint is already scheduled at the end of the current frame.
+    Scheduled,
+}
+
+const MIN_FPS: f32 = 1.0;
+const MAX_FPS: f32 = 120.0;
+
 impl App for RiemannDashApp {
+    /// Saves the state as JSON, RON can't read back the unit variants of the views.
+    fn save(&mut self, storage: &mut dyn Storage) {
+        match serde_json::to_string(self) {
+            Ok(state) => storage.set_string(eframe::APP_KEY, state),
+            Err(e) => tracing::error!("could not save the dashboard: {}", e),
+        }
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut Frame) {
-        egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
-            egui::menu::bar(ui, |ui| {
-                egui::widgets::global_dark_light_mode_switch(ui);
+        self.frames.frame_started(self.max_fps);
 
-                ui.separator();
+        if !self.kiosk {
+            egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
+                egui::menu::bar(ui, |ui| {
+                    egui::widgets::global_dark_light_mode_switch(ui);
 
-                for (i, workspace) in self.workspaces.iter_mut().enumerate() {
-                    if ui.selectable_label(self.selected_workspace == i, &workspace.name).clicked()
+                    ui.separator();
+
+                    for (i, workspace) in self.workspaces.iter_mut().enumerate() {
+                        if ui
+                            .selectable_label(self.selected_workspace == i, &workspace.name)
+                            .clicked()
+                        {
+                            workspace.reset_confirm_delete();
+                            self.selected_workspace = i;
+                        }
+                    }
+
+                    if ui.button("+").clicked() {
+                        self.workspaces.push(Workspace::default());
+                        self.selected_workspace = self.workspaces.len().saturating_sub(1);
+                    }
+
+                    if ui
+                        .selectable_label(self.dash_importer.is_some(), "⬇")
+                        .on_hover_text(dash_config::DEFAULT_TITLE)
+                        .clicked()
                     {
-                        workspace.reset_confirm_delete();
-                        self.selected_workspace = i;
+                        self.dash_importer = match self.dash_importer {
+                            Some(_) => None,
+                            None => Some(DashConfigImporter::default()),
+                        };
                     }
-                }
 
-                if ui.button("+").clicked() {
-                    self.workspaces.push(Workspace::default());
-                    self.selected_workspace = self.workspaces.len().saturating_sub(1);
-                }
+                    ui.separator();
 
-                ui.separator();
+                    ui.add(
+                        egui::DragValue::new(&mut self.max_fps)
+                            .clamp_range(MIN_FPS..=MAX_FPS)
+                            .suffix(" fps"),
+                    )
+                    .on_hover_text("Maximum frame rate");
 
-                let valid_editable_url = Url::parse(&self.editable_url).is_ok();
-                let lost_focus = ui
-                    .with_layout(Layout::left_to_right(), |ui| {
-                        if !valid_editable_url {
-                            ui.style_mut().visuals.extreme_bg_color = Color32::LIGHT_RED;
+                    ui.separator();
+
+                    let valid_editable_url = Url::parse(&self.editable_url).is_ok();
+                    let lost_focus = ui
+                        .with_layout(Layout::left_to_right(), |ui| {
+                            if !valid_editable_url {
+                                ui.style_mut().visuals.extreme_bg_color = Color32::LIGHT_RED;
+                            }
+                            ui.text_edit_singleline(&mut self.editable_url).lost_focus()
+                        })
+                        .inner;
+
+                    if lost_focus || ui.input().key_pressed(egui::Key::Enter) {
+                        match Url::parse(&self.editable_url) {
+                            Ok(url) => self.valid_url = url,
+                            Err(e) => eprintln!("{}", e),
                         }
-                        ui.text_edit_singleline(&mut self.editable_url).lost_focus()
-                    })
-                    .inner;
-
-                if lost_focus || ui.input().key_pressed(egui::Key::Enter) {
-                    match Url::parse(&self.editable_url) {
-                        Ok(url) => self.valid_url = url,
-                        Err(e) => eprintln!("{}", e),
                     }
-                }
+                });
             });
-        });
+        }
+
+        if let Some(importer) = self.dash_importer.as_mut() {
+            let mut open = true;
+            let workspaces = importer.show(ctx, &mut open);
+            if !workspaces.is_empty() {
+                self.selected_workspace = self.workspaces.len();
+                self.workspaces.extend(workspaces);
+            }
+            if !open {
+                self.dash_importer = None;
+            }
+        }
+
+        if self.subscriptions.url() != &self.valid_url {
+            self.subscriptions.set_url(self.valid_url.clone());
+        }
+        self.subscriptions.poll();
 
         self.selected_workspace =
             self.selected_workspace.min(self.workspaces.len().saturating_sub(1));
         match self.workspaces.get_mut(self.selected_workspace) {
             Some(workspace) => {
                 let mut open = true;
-                workspace.ui(Id::new(self.selected_workspace), &self.valid_url, &mut open, ctx);
+                let id = Id::new(self.selected_workspace);
+                workspace.ui(id, &mut self.subscriptions, self.kiosk, &mut open, ctx);
+                let imported = workspace.take_imported();
                 if !open {
                     self.workspaces.remove(self.selected_workspace);
                 }
+                // the imported workspaces are added like the ones of the other importers
+                if let Some(imported) = imported {
+                    self.workspaces.push(imported);
+                    self.selected_workspace = self.workspaces.len() - 1;
+                }
             }
             None => {
                 egui::CentralPanel::default().show(ctx, |ui| {
@@ -77,12 +283,121 @@ impl App for RiemannDashApp {
 
 impl Default for RiemannDashApp {
     fn default() -> Self {
-        let url = Url::parse("ws://163.172.181.220:5556").unwrap(); // ws://localhost:5556
+        let url = Url::parse(crate::DEFAULT_URL).unwrap();
         Self {
             editable_url: url.as_str().to_string(),
+            subscriptions: Subscriptions::new(url.clone()),
             valid_url: url,
             selected_workspace: 0,
             workspaces: vec![Workspace::default()],
+            max_fps: 30.0,
+            frames: Default::default(),
+            dash_importer: None,
+            kiosk: false,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    /// Writes the strings in a RON document when flushed, like the file storage of eframe.
+    #[derive(Default)]
+    struct MemoryStorage {
+        kv: HashMap<String, String>,
+        file: String,
+    }
+
+    impl MemoryStorage {
+        /// Reads the flushed document, like eframe at startup.
+        fn reopen(&self) -> MemoryStorage {
+            MemoryStorage { kv: ron::from_str(&self.file).unwrap(), file: self.file.clone() }
+        }
+    }
+
+    impl Storage for MemoryStorage {
+        fn get_string(&self, key: &str) -> Option<String> {
+            self.kv.get(key).cloned()
+        }
+
+        fn set_string(&mut self, key: &str, value: String) {
+            self.kv.insert(key.to_string(), value);
+        }
+
+        fn flush(&mut self) {
+            self.file = ron::ser::to_string_pretty(&self.kv, Default::default()).unwrap();
+        }
+    }
+
+    const WORKSPACE: &str = r#"{
+        "version": 1,
+        "name": "every view",
+        "views": [
+            { "type": "Log", "query": "service = \"log\"" },
+            {
+                "type": "Flot",
+                "query": "service = \"flot\"",
+                "retention": "Messages",
+                "group_by": { "Attribute": "dc" },
+                "transform": "Rate",
+                "render": "StackedArea",
+                "time_zone": "Utc"
+            },
+            {
+                "type": "BigNumber",
+                "query": "service = \"big\"",
+                "format": "Bytes",
+                "aggregation": "Sum",
+                "expiry": "Remove"
+            },
+            { "type": "Gauge", "query": "service = \"gauge\"", "style": "Bar", "expiry": "Ignore" },
+            {
+                "type": "Grid",
+                "query": "service = \"grid\"",
+                "row_sort": "WorstState",
+                "column_sort": "Descending",
+                "expiry": "Grey"
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn repaints_wait_for_the_end_of_the_frame() {
+        let limiter = FrameLimiter::default();
+        assert_eq!(limiter.next_repaint(), Repaint::Now);
+
+        limiter.frame_started(MIN_FPS);
+        assert!(
+            matches!(limiter.next_repaint(), Repaint::After(remaining) if remaining.as_secs_f32() > 0.5)
+        );
+        assert_eq!(limiter.next_repaint(), Repaint::Scheduled);
+    }
+
+    #[test]
+    fn save_and_restore_every_view() {
+        let mut app = RiemannDashApp::default();
+        app.load_workspace(Workspace::from_json(WORKSPACE).unwrap());
+        let expected = serde_json::to_value(&app).unwrap();
+
+        let mut storage = MemoryStorage::default();
+        app.save(&mut storage);
+        storage.flush();
+
+        let restored = RiemannDashApp::restore(&storage.reopen());
+        assert_eq!(restored.workspaces.len(), 2);
+        assert_eq!(serde_json::to_value(&restored).unwrap(), expected);
+    }
+
+    #[test]
+    fn restore_previous_ron_state() {
+        let mut storage = MemoryStorage::default();
+        let app = RiemannDashApp { max_fps: 12.0, ..Default::default() };
+        eframe::set_value(&mut storage, eframe::APP_KEY, &app);
+        storage.flush();
+
+        assert_eq!(RiemannDashApp::restore(&storage.reopen()).max_fps, 12.0);
+    }
+}
@@ -1,18 +1,13 @@
 use eframe::egui;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Default)]
 pub enum Clicked {
+    #[default]
     None,
     Once,
     Twice,
 }
 
-impl Default for Clicked {
-    fn default() -> Self {
-        Clicked::None
-    }
-}
-
 impl Clicked {
     pub fn confirm_clicked(&self) -> bool {
         matches!(self, Clicked::Twice)
@@ -0,0 +1,59 @@
+use eframe::egui;
+use eframe::egui::{Color32, RichText};
+
+use crate::event::ConnectionState;
+use crate::subscriptions::Subscription;
+
+/// A badge displaying the state of the connection of a view and when the last message arrived.
+pub struct ConnectionStatus<'a> {
+    subscription: Option<&'a Subscription>,
+    connect_error: Option<&'a str>,
+}
+
+impl<'a> ConnectionStatus<'a> {
+    pub fn new(subscription: Option<&'a Subscription>) -> Self {
+        ConnectionStatus { subscription, connect_error: None }
+    }
+
+    /// The error returned when the connection couldn't even be started.
+    pub fn connect_error(self, error: Option<&'a str>) -> Self {
+        Self { connect_error: error, ..self }
+    }
+}
+
+impl egui::Widget for ConnectionStatus<'_> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let state = self.subscription.map(|s| s.state());
+        let (color, text) = match (self.connect_error, &state) {
+            (Some(error), _) => (Color32::LIGHT_RED, format!("error: {}", error)),
+            (None, None) => (Color32::GRAY, "no query".to_string()),
+            (None, Some(ConnectionState::Connecting)) => {
+                (Color32::YELLOW, "connecting".to_string())
+            }
+            (None, Some(ConnectionState::Open)) => (Color32::GREEN, "live".to_string()),
+            (None, Some(ConnectionState::Reconnecting { error: Some(error), .. }))
+            | (None, Some(ConnectionState::Closed { error: Some(error) })) => {
+                (Color32::LIGHT_RED, format!("error: {}", error))
+            }
+            (None, Some(ConnectionState::Reconnecting { error: None, .. }))
+            | (None, Some(ConnectionState::Closed { error: None })) => {
+                (Color32::GRAY, "closed".to_string())
+            }
+        };
+
+        let last_message = self.subscription.and_then(|s| s.last_message());
+
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("●").color(color));
+            ui.label(RichText::new(text).color(color));
+            if let Some(ConnectionState::Reconnecting { attempt, .. }) = state {
+                ui.weak(format!("(retry #{})", attempt + 1));
+            }
+            if let Some(last_message) = last_message {
+                let elapsed = last_message.elapsed().as_secs();
+                ui.weak(format!("last message {}s ago", elapsed));
+            }
+        })
+        .response
+    }
+}
@@ -0,0 +1,274 @@
+//! Converts the `config.json` of the Ruby riemann-dash into workspaces.
+
+use eframe::egui;
+use eframe::egui::{Color32, RichText, TextEdit};
+use serde_json::Value;
+
+use crate::views::*;
+use crate::workspace::Workspace;
+
+pub const DEFAULT_TITLE: &str = "Import riemann-dash config";
+
+/// The workspaces converted from a riemann-dash config along with
+/// a description of every view that could not be converted.
+pub struct DashConfig {
+    pub workspaces: Vec<Workspace>,
+    pub unmapped: Vec<String>,
+}
+
+impl DashConfig {
+    pub fn from_json(json: &str) -> anyhow::Result<DashConfig> {
+        let config: Value = serde_json::from_str(json)?;
+        let workspaces = match config.get("workspaces") {
+            Some(Value::Array(workspaces)) => workspaces,
+            _ => anyhow::bail!("the config doesn't contain a list of workspaces"),
+        };
+
+        let mut unmapped = Vec::new();
+        let workspaces = workspaces
+            .iter()
+            .map(|workspace| {
+                let name = str_field(workspace, "name").unwrap_or("Riemann").to_string();
+                let mut converted = Converted::default();
+                if let Some(view) = workspace.get("view") {
+                    converted.collect(view, &name, &mut unmapped);
+                }
+                Workspace::new(name, converted.help, converted.views)
+            })
+            .collect();
+
+        Ok(DashConfig { workspaces, unmapped })
+    }
+}
+
+#[derive(Default)]
+struct Converted {
+    help: bool,
+    views: Vec<AnyView>,
+}
+
+impl Converted {
+    /// Walks the riemann-dash view tree, containers are flattened as
+    /// the egui windows are free to be arranged in the workspace.
+    fn collect(&mut self, view: &Value, workspace: &str, unmapped: &mut Vec<String>) {
+        let query = || str_field(view, "query").unwrap_or_default().to_string();
+        let title = || str_field(view, "title").unwrap_or_default().to_string();
+
+        match str_field(view, "type").unwrap_or_default() {
+            "Balloon" | "Fullscreen" => {
+                if let Some(child) = view.get("child") {
+                    self.collect(child, workspace, unmapped);
+                }
+            }
+            "HStack" | "VStack" => {
+                let children = view.get("children").and_then(Value::as_array);
+                for child in children.into_iter().flatten() {
+                    self.collect(child, workspace, unmapped);
+                }
+            }
+            "Help" => self.help = true,
+            "Log" => {
+                let limit = match view.get("lines") {
+                    Some(Value::Number(lines)) => lines.as_f64(),
+                    Some(Value::String(lines)) => lines.parse().ok(),
+                    _ => None,
+                };
+                let limit = limit.unwrap_or(1000.0) as f32;
+                self.views.push(AnyView::Log(log::Log::new(title(), query(), limit)));
+            }
+            "Flot" | "Timeseries" => {
+                self.views.push(AnyView::Flot(flot::Flot::new(title(), query())))
+            }
+            "Gauge" => {
+                self.views.push(AnyView::BigNumber(big_number::BigNumber::new(title(), query())))
+            }
+            "Grid" => self.views.push(AnyView::Grid(grid::Grid::new(title(), query()))),
+            "Dial" => {
+                let max = match view.get("max") {
+                    Some(Value::Number(max)) => max.as_f64(),
+                    Some(Value::String(max)) => max.parse().ok(),
+                    _ => None,
+                };
+                let max = max.unwrap_or(100.0) as f32;
+                self.views.push(AnyView::Gauge(gauge::Gauge::new(title(), query(), max)));
+            }
+            other => {
+                let title = title();
+                let kind = if other.is_empty() { "untyped view" } else { other };
+                let description = if title.is_empty() {
+                    format!("{}: {}", workspace, kind)
+                } else {
+                    format!("{}: {} {:?}", workspace, kind, title)
+                };
+                unmapped.push(description);
+            }
+        }
+    }
+}
+
+fn str_field<'a>(value: &'a Value, name: &str) -> Option<&'a str> {
+    value.get(name).and_then(Value::as_str)
+}
+
+/// The window used to paste or load a riemann-dash config.
+#[derive(Default)]
+pub struct DashConfigImporter {
+    /// The file path on native, the pasted config on the web.
+    input: String,
+    report: Option<Result<Vec<String>, String>>,
+}
+
+impl DashConfigImporter {
+    /// Shows the importer window and returns the imported workspaces, if any.
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) -> Vec<Workspace> {
+        let mut workspaces = Vec::new();
+        egui::Window::new(DEFAULT_TITLE).open(open).show(ctx, |ui| {
+            #[cfg(not(target_arch = "wasm32"))]
+            ui.add(TextEdit::singleline(&mut self.input).hint_text("config.json"));
+
+            #[cfg(target_arch = "wasm32")]
+            ui.add(
+                TextEdit::multiline(&mut self.input)
+                    .hint_text("paste the riemann-dash config.json")
+                    .desired_rows(4),
+            );
+
+            if ui.button("Import").clicked() {
+                #[cfg(not(target_arch = "wasm32"))]
+                let result = std::fs::read_to_string(&self.input)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|json| DashConfig::from_json(&json));
+
+                #[cfg(target_arch = "wasm32")]
+                let result = DashConfig::from_json(&self.input);
+
+                self.report = Some(match result {
+                    Ok(config) => {
+                        workspaces = config.workspaces;
+                        Ok(config.unmapped)
+                    }
+                    Err(e) => Err(e.to_string()),
+                });
+            }
+
+            match &self.report {
+                Some(Ok(unmapped)) if unmapped.is_empty() => {
+                    ui.label("Every view has been imported.");
+                }
+                Some(Ok(unmapped)) => {
+                    ui.label("These views could not be imported:");
+                    for description in unmapped {
+                        ui.label(RichText::new(description).color(Color32::LIGHT_RED));
+                    }
+                }
+                Some(Err(error)) => {
+                    ui.label(RichText::new(error).color(Color32::LIGHT_RED));
+                }
+                None => (),
+            }
+        });
+        workspaces
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// The type, title and query of the views of the workspace.
+    fn views(workspace: &Workspace) -> Vec<(String, String, String)> {
+        let workspace = serde_json::to_value(workspace).unwrap();
+        let views = workspace["views"].as_array().unwrap();
+        views
+            .iter()
+            .map(|view| {
+                let field = |name| view[name].as_str().unwrap_or_default().to_string();
+                (field("type"), field("title"), field("query"))
+            })
+            .collect()
+    }
+
+    fn view(kind: &str, title: &str, query: &str) -> Value {
+        json!({ "type": kind, "title": title, "query": query })
+    }
+
+    #[test]
+    fn nested_containers_are_flattened() {
+        let config = json!({ "workspaces": [{
+            "name": "Nested",
+            "view": { "type": "HStack", "children": [
+                { "type": "Balloon", "child": view("Log", "errors", "state = \"critical\"") },
+                { "type": "VStack", "children": [
+                    view("Grid", "hosts", "true"),
+                    { "type": "Fullscreen", "child": view("Dial", "cpu", "service = \"cpu\"") },
+                ]},
+            ]},
+        }]});
+        let config = DashConfig::from_json(&config.to_string()).unwrap();
+
+        assert!(config.unmapped.is_empty());
+        assert_eq!(config.workspaces.len(), 1);
+        assert_eq!(config.workspaces[0].name, "Nested");
+        let kinds: Vec<_> = views(&config.workspaces[0]).into_iter().map(|v| v.0).collect();
+        assert_eq!(kinds, ["Log", "Grid", "Gauge"]);
+    }
+
+    #[test]
+    fn every_view_type_is_mapped() {
+        let children = [
+            view("Log", "log", "true"),
+            view("Flot", "flot", "service = \"cpu\""),
+            view("Timeseries", "timeseries", "service = \"memory\""),
+            view("Gauge", "gauge", "service = \"load\""),
+            view("Grid", "grid", "state != \"ok\""),
+            view("Dial", "dial", "service = \"disk\""),
+        ];
+        let config = json!({ "workspaces": [{
+            "name": "Types",
+            "view": { "type": "VStack", "children": children },
+        }]});
+        let config = DashConfig::from_json(&config.to_string()).unwrap();
+
+        assert!(config.unmapped.is_empty());
+        let expected = [
+            ("Log", "log", "true"),
+            ("Flot", "flot", "service = \"cpu\""),
+            ("Flot", "timeseries", "service = \"memory\""),
+            ("BigNumber", "gauge", "service = \"load\""),
+            ("Grid", "grid", "state != \"ok\""),
+            ("Gauge", "dial", "service = \"disk\""),
+        ];
+        let expected: Vec<_> = expected
+            .into_iter()
+            .map(|(kind, title, query)| (kind.to_string(), title.to_string(), query.to_string()))
+            .collect();
+        assert_eq!(views(&config.workspaces[0]), expected);
+    }
+
+    #[test]
+    fn unmapped_views_are_reported() {
+        let config = json!({ "workspaces": [{
+            "name": "Unknown",
+            "view": { "type": "HStack", "children": [
+                view("Geckoboard", "sales", "true"),
+                { "type": "Iframe" },
+                {},
+                view("Log", "", "true"),
+            ]},
+        }]});
+        let config = DashConfig::from_json(&config.to_string()).unwrap();
+
+        let expected =
+            ["Unknown: Geckoboard \"sales\"", "Unknown: Iframe", "Unknown: untyped view"];
+        assert_eq!(config.unmapped, expected);
+        assert_eq!(views(&config.workspaces[0]).len(), 1);
+    }
+
+    #[test]
+    fn configs_without_workspaces_are_rejected() {
+        assert!(DashConfig::from_json("{}").is_err());
+        assert!(DashConfig::from_json("not json").is_err());
+    }
+}
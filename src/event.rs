@@ -1,35 +1,223 @@
 use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 
 use ewebsock::{WsEvent, WsReceiver, WsSender};
+use instant::Instant;
 use serde::{Deserialize, Deserializer};
 use serde_json::Value;
 use url::Url;
 
+/// The delay before the first reconnection attempt, doubled after each failure.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// The state of the websocket connection of an [`EventReceiver`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// Waiting for the websocket to be opened.
+    Connecting,
+    /// The websocket is opened, events are flowing.
+    Open,
+    /// The websocket was closed or errored, waiting before the next attempt.
+    Reconnecting { attempt: u32, error: Option<String> },
+    /// The websocket was closed and won't be reopened, like after a one-shot index query.
+    Closed { error: Option<String> },
+}
+
+/// A websocket connection to a Riemann server that reconnects
+/// with an exponential backoff, the subscribed query is preserved.
 pub struct EventReceiver {
     url: Url,
+    wakeup: Arc<dyn Fn() + Send + Sync>,
     // don't drop the sender or the connection will be closed
-    _sender: WsSender,
-    receiver: WsReceiver,
+    connection: Option<(WsSender, WsReceiver)>,
+    state: ConnectionState,
+    retry_at: Option<Instant>,
+    last_message: Option<Instant>,
+    reconnect: bool,
 }
 
 impl EventReceiver {
     pub fn connect(url: Url, wakeup: impl Fn() + Send + Sync + 'static) -> ewebsock::Result<Self> {
-        match ewebsock::connect_with_wakeup(url.as_str(), wakeup) {
-            Ok((sender, receiver)) => Ok(EventReceiver { url, _sender: sender, receiver }),
-            Err(e) => Err(e),
-        }
+        let wakeup: Arc<dyn Fn() + Send + Sync> = Arc::new(wakeup);
+        let connection = open(&url, &wakeup)?;
+        Ok(EventReceiver {
+            url,
+            wakeup,
+            connection: Some(connection),
+            state: ConnectionState::Connecting,
+            retry_at: None,
+            last_message: None,
+            reconnect: true,
+        })
+    }
+
+    /// Connects without reconnecting once the server closes the websocket,
+    /// used for the one-shot index queries (`subscribe=false`).
+    pub fn connect_once(
+        url: Url,
+        wakeup: impl Fn() + Send + Sync + 'static,
+    ) -> ewebsock::Result<Self> {
+        let receiver = EventReceiver::connect(url, wakeup)?;
+        Ok(EventReceiver { reconnect: false, ..receiver })
     }
 
     pub fn url(&self) -> &Url {
         &self.url
     }
 
-    pub fn try_recv(&self) -> Option<WsEvent> {
-        self.receiver.try_recv()
+    pub fn state(&self) -> &ConnectionState {
+        &self.state
+    }
+
+    /// The moment the last message was received.
+    pub fn last_message(&self) -> Option<Instant> {
+        self.last_message
+    }
+
+    /// Returns the next websocket event and reconnects when the connection is lost
+    /// and the backoff delay elapsed, unless connected once.
+    /// `Error` and `Closed` events are still returned.
+    pub fn try_recv(&mut self) -> Option<WsEvent> {
+        if let Some(retry_at) = self.retry_at {
+            if Instant::now() < retry_at {
+                return None;
+            }
+            self.retry_at = None;
+            match open(&self.url, &self.wakeup) {
+                Ok(connection) => self.connection = Some(connection),
+                Err(e) => {
+                    self.schedule_reconnect(Some(e));
+                    return None;
+                }
+            }
+        }
+
+        let (_sender, receiver) = self.connection.as_ref()?;
+        let event = receiver.try_recv()?;
+        match &event {
+            WsEvent::Opened => self.state = ConnectionState::Open,
+            WsEvent::Message(_) => self.last_message = Some(Instant::now()),
+            WsEvent::Error(e) => self.closed(Some(e.clone())),
+            WsEvent::Closed => self.closed(None),
+        }
+        Some(event)
+    }
+
+    fn closed(&mut self, error: Option<String>) {
+        if self.reconnect {
+            self.schedule_reconnect(error);
+        } else {
+            self.connection = None;
+            self.state = ConnectionState::Closed { error };
+        }
+    }
+
+    fn schedule_reconnect(&mut self, error: Option<String>) {
+        let attempt = match self.state {
+            ConnectionState::Reconnecting { attempt, .. } => attempt + 1,
+            _ => 0,
+        };
+
+        let backoff = backoff(attempt);
+        self.connection = None;
+        self.retry_at = Some(Instant::now() + backoff);
+        self.state = ConnectionState::Reconnecting { attempt, error };
+
+        // nobody polls the receiver when no events arrive, wake them up when it's time
+        wakeup_after(backoff, self.wakeup.clone());
+    }
+}
+
+/// The delay before the reconnection attempt, starting at 0.
+pub fn backoff(attempt: u32) -> Duration {
+    INITIAL_BACKOFF.saturating_mul(2u32.saturating_pow(attempt)).min(MAX_BACKOFF)
+}
+
+/// A wakeup function waiting for its moment on the timer thread.
+#[cfg(not(target_arch = "wasm32"))]
+type Timeout = (Instant, Arc<dyn Fn() + Send + Sync>);
+
+/// Calls the wakeup function after this delay, with a single timer thread
+/// on native and with a timeout of the browser on the web.
+pub fn wakeup_after(delay: Duration, wakeup: Arc<dyn Fn() + Send + Sync>) {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use std::sync::mpsc::{channel, Sender};
+
+        static TIMER: OnceLock<Sender<Timeout>> = OnceLock::new();
+        let timer = TIMER.get_or_init(|| {
+            let (sender, receiver) = channel();
+            std::thread::Builder::new()
+                .name("wakeup timer".to_string())
+                .spawn(move || run_timer(receiver))
+                .expect("could not spawn the wakeup timer thread");
+            sender
+        });
+        if timer.send((Instant::now() + delay, wakeup)).is_err() {
+            tracing::warn!("could not schedule a wakeup in {:?}", delay);
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        use eframe::wasm_bindgen::closure::Closure;
+        use eframe::wasm_bindgen::JsCast;
+
+        let callback = Closure::once_into_js(move || wakeup());
+        let millis = delay.as_millis().min(i32::MAX as u128) as i32;
+        let scheduled = eframe::web_sys::window().map(|window| {
+            window.set_timeout_with_callback_and_timeout_and_arguments_0(
+                callback.unchecked_ref(),
+                millis,
+            )
+        });
+        if !matches!(scheduled, Some(Ok(_))) {
+            tracing::warn!("could not schedule a wakeup in {:?}", delay);
+        }
+    }
+}
+
+/// Calls the wakeup functions sent to the timer when their moment comes.
+#[cfg(not(target_arch = "wasm32"))]
+fn run_timer(receiver: std::sync::mpsc::Receiver<Timeout>) {
+    use std::sync::mpsc::RecvTimeoutError;
+
+    let mut pending: Vec<Timeout> = Vec::new();
+    loop {
+        let next = pending.iter().map(|(at, _)| *at).min();
+        let received = match next {
+            Some(next) => receiver.recv_timeout(next.saturating_duration_since(Instant::now())),
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(timeout) => pending.push(timeout),
+            Err(RecvTimeoutError::Timeout) => (),
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+
+        let now = Instant::now();
+        pending.retain(|(at, wakeup)| {
+            let due = *at <= now;
+            if due {
+                wakeup();
+            }
+            !due
+        });
     }
 }
 
+fn open(
+    url: &Url,
+    wakeup: &Arc<dyn Fn() + Send + Sync>,
+) -> ewebsock::Result<(WsSender, WsReceiver)> {
+    let wakeup = wakeup.clone();
+    ewebsock::connect_with_wakeup(url.as_str(), move || wakeup())
+}
+
 #[derive(Debug, Deserialize)]
+#[allow(dead_code)] // not every field is displayed by the views
 pub struct Event {
     #[serde(deserialize_with = "time::serde::iso8601::option::deserialize")]
     pub time: Option<time::OffsetDateTime>,
@@ -46,6 +234,47 @@ pub struct Event {
     pub attributes: HashMap<String, Value>,
 }
 
+impl Event {
+    /// The moment the event expires in seconds since the Unix epoch, if it has a time and a TTL.
+    pub fn expires_at(&self) -> Option<f64> {
+        let time = self.time?.unix_timestamp_nanos() as f64 / 1e9;
+        Some(time + f64::from(self.ttl?))
+    }
+
+    /// Whether this event can replace the other one as the latest of a host or a service,
+    /// the events of the index may arrive after newer live ones.
+    pub fn is_newer_than(&self, other: &Event) -> bool {
+        match (self.time, other.time) {
+            (Some(time), Some(other)) => time >= other,
+            _ => true,
+        }
+    }
+
+    pub fn is_expired(&self, now: f64) -> bool {
+        self.expires_at().is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// The current time in seconds since the Unix epoch, also available on the web.
+pub fn unix_now() -> f64 {
+    let now = instant::SystemTime::now().duration_since(instant::SystemTime::UNIX_EPOCH);
+    now.map_or(0.0, |elapsed| elapsed.as_secs_f64())
+}
+
+static LOCAL_OFFSET: OnceLock<time::UtcOffset> = OnceLock::new();
+
+/// Determines the offset of the local time zone, it can only be
+/// determined on unix while the process has a single thread.
+pub fn init_local_offset() {
+    local_offset();
+}
+
+/// The offset of the local time zone, UTC when it is unknown.
+pub fn local_offset() -> time::UtcOffset {
+    *LOCAL_OFFSET
+        .get_or_init(|| time::UtcOffset::current_local_offset().unwrap_or(time::UtcOffset::UTC))
+}
+
 fn deserialize_collections<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
 where
     D: Deserializer<'de>,
@@ -54,3 +283,25 @@ where
     // Deserialize null to empty Vec
     Deserialize::deserialize(deserializer).or(Ok(vec![]))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn wakeups_are_called_in_order() {
+        let (sender, receiver) = channel();
+        let sender = Arc::new(Mutex::new(sender));
+        for (delay, name) in [(60, "last"), (20, "first"), (40, "second")] {
+            let sender = sender.clone();
+            let wakeup = move || sender.lock().unwrap().send(name).unwrap();
+            wakeup_after(Duration::from_millis(delay), Arc::new(wakeup));
+        }
+
+        let names: Vec<_> = receiver.iter().take(3).collect();
+        assert_eq!(names, ["first", "second", "last"]);
+    }
+}
@@ -3,14 +3,24 @@
 
 mod app;
 mod confirm_button;
+mod connection_status;
+mod dash_config;
 mod event;
+mod query;
+mod query_builder;
+mod query_editor;
+mod query_filter;
+mod query_subscription;
+mod subscriptions;
 mod views;
 mod workspace;
 
 pub use app::RiemannDashApp;
 #[cfg(target_arch = "wasm32")] // When compiling for web
 use eframe::wasm_bindgen::{self, prelude::*};
+pub use event::{init_local_offset, Event, EventReceiver};
 use url::Url;
+pub use workspace::Workspace;
 
 /// This is the entry-point for all the web-assembly.
 /// This is called once from the HTML.
@@ -25,10 +35,12 @@ pub fn start(canvas_id: &str) -> std::result::Result<(), eframe::wasm_bindgen::J
     // Redirect tracing to console.log and friends:
     tracing_wasm::set_as_global_default();
 
-    let app = RiemannDashApp::default();
-    eframe::start_web(canvas_id, Box::new(app))
+    eframe::start_web(canvas_id, Box::new(|cc| Box::new(RiemannDashApp::new(cc))))
 }
 
+/// The Riemann server used when none is specified.
+pub const DEFAULT_URL: &str = "ws://localhost:5556";
+
 pub fn websocket_url(url: &Url, subscribe: bool, query: &str) -> Url {
     use url::form_urlencoded::Serializer;
 
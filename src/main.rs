@@ -2,15 +2,133 @@
 #![cfg_attr(not(debug_assertions), deny(warnings))] // Forbid warnings in release builds
 #![warn(clippy::all, rust_2018_idioms)]
 
+#[cfg(not(target_arch = "wasm32"))]
+mod tail;
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+
+#[cfg(not(target_arch = "wasm32"))]
+use anyhow::Context;
+#[cfg(not(target_arch = "wasm32"))]
+use clap::{CommandFactory, ErrorKind, Parser};
+#[cfg(not(target_arch = "wasm32"))]
+use url::Url;
+
+/// An alternative dashboard for a Riemann monitoring system.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Parser)]
+#[clap(version, about)]
+struct Cli {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    /// The websocket URL of the Riemann server, e.g. ws://localhost:5556,
+    /// the dashboard saves it in place of the previous one.
+    #[clap(long, global = true)]
+    url: Option<Url>,
+
+    /// Workspaces exported from the dashboard to load at startup, they replace
+    /// the saved workspaces with the same names and are saved with the others.
+    #[clap(long = "workspace", value_name = "FILE")]
+    workspaces: Vec<PathBuf>,
+
+    /// The name of the workspace to show at startup.
+    #[clap(long, value_name = "NAME")]
+    select: Option<String>,
+
+    /// Only show the selected workspace views in a maximized undecorated window.
+    #[clap(long)]
+    kiosk: bool,
+
+    /// The maximum number of frames per second, new events trigger a repaint,
+    /// the dashboard saves it like the setting of its top panel.
+    #[clap(long, value_name = "FPS")]
+    max_fps: Option<f32>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Prints the events matching a query without opening a window.
+    Tail {
+        /// The Riemann query the events must match, e.g. 'service = "cpu"'.
+        query: String,
+
+        /// Prints the events as newline-delimited JSON.
+        #[clap(long)]
+        ndjson: bool,
+    },
+}
+
 // When compiling natively:
 #[cfg(not(target_arch = "wasm32"))]
-#[tokio::main]
-async fn main() {
+fn main() -> anyhow::Result<()> {
+    // must be done before the runtime spawns its threads
+    riemann_egui_dash::init_local_offset();
+    tokio::runtime::Runtime::new()?.block_on(run())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn run() -> anyhow::Result<()> {
+    let Cli { command, url, workspaces, select, kiosk, max_fps } = Cli::parse();
+
+    if let Some(Command::Tail { query, ndjson }) = command {
+        let gui_args = [
+            ("--workspace", !workspaces.is_empty()),
+            ("--select", select.is_some()),
+            ("--kiosk", kiosk),
+            ("--max-fps", max_fps.is_some()),
+        ];
+        if let Some((arg, _)) = gui_args.into_iter().find(|(_, used)| *used) {
+            let message =
+                format!("The argument '{}' cannot be used with the 'tail' subcommand", arg);
+            Cli::command().error(ErrorKind::ArgumentConflict, message).exit();
+        }
+
+        // the standard output is reserved to the events
+        tracing_subscriber::fmt().with_writer(std::io::stderr).init();
+        let url = match url {
+            Some(url) => url,
+            None => Url::parse(riemann_egui_dash::DEFAULT_URL)?,
+        };
+        return tail::tail(&url, &query, ndjson).await;
+    }
+
     tracing_subscriber::fmt::init();
-    let native_options = eframe::NativeOptions::default();
+    let workspaces = workspaces
+        .iter()
+        .map(|path| {
+            std::fs::read_to_string(path)
+                .map_err(anyhow::Error::from)
+                .and_then(|json| riemann_egui_dash::Workspace::from_json(&json))
+                .with_context(|| format!("while loading {}", path.display()))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let native_options =
+        eframe::NativeOptions { maximized: kiosk, decorated: !kiosk, ..Default::default() };
     eframe::run_native(
         "Riemann egui dashboard",
         native_options,
-        Box::new(|_ctx| Box::new(riemann_egui_dash::RiemannDashApp::default())),
+        Box::new(move |cc| {
+            let mut app = riemann_egui_dash::RiemannDashApp::new(cc);
+            if let Some(url) = url {
+                app.set_url(url);
+            }
+            for workspace in workspaces {
+                app.load_workspace(workspace);
+            }
+            if let Some(name) = select {
+                if !app.select_workspace(&name) {
+                    tracing::warn!("there is no workspace named {:?}", name);
+                }
+            }
+            if let Some(max_fps) = max_fps {
+                app.set_max_fps(max_fps);
+            }
+            app.set_kiosk(kiosk);
+            Box::new(app)
+        }),
     );
 }
@@ -0,0 +1,685 @@
+//! A parser and an evaluator for the Riemann query language.
+//!
+//! It follows [the grammar of the Riemann server](https://github.com/riemann/riemann/blob/master/resources/query.g4),
+//! the queries are validated before being sent to the server and can be matched against
+//! the events already received to filter them without subscribing again.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Range;
+
+use regex::Regex;
+
+use crate::event::Event;
+
+/// A parsed Riemann query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    True,
+    False,
+    Tagged(String),
+    Compare { field: Field, op: Op, value: Value },
+    Not(Box<Query>),
+    And(Vec<Query>),
+    Or(Vec<Query>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Field {
+    Host,
+    Service,
+    State,
+    Description,
+    /// Both `metric` and `metric_f`.
+    Metric,
+    Time,
+    Ttl,
+    /// Any other field, stored in the custom attributes of the events.
+    Custom(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    /// `=~`, a SQL-like pattern where `%` matches any sequence of characters.
+    Like,
+    /// `~=`, a regular expression.
+    Regex,
+}
+
+impl Op {
+    pub const ALL: &'static [Op] = &[
+        Op::Equal,
+        Op::NotEqual,
+        Op::Less,
+        Op::LessEqual,
+        Op::Greater,
+        Op::GreaterEqual,
+        Op::Like,
+        Op::Regex,
+    ];
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Op::Equal => "=",
+            Op::NotEqual => "!=",
+            Op::Less => "<",
+            Op::LessEqual => "<=",
+            Op::Greater => ">",
+            Op::GreaterEqual => ">=",
+            Op::Like => "=~",
+            Op::Regex => "~=",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Number(f64),
+    Bool(bool),
+    Nil,
+    /// The compiled value of the `=~` and `~=` operators.
+    Pattern(Pattern),
+}
+
+/// A compiled pattern, compared by its source.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    source: String,
+    regex: Regex,
+}
+
+impl Pattern {
+    /// A SQL-like pattern where `%` matches any sequence of characters, the whole string must match.
+    pub fn like(source: &str) -> Pattern {
+        let parts: Vec<_> = source.split('%').map(regex::escape).collect();
+        let regex = Regex::new(&format!("^{}$", parts.join(".*"))).unwrap();
+        Pattern { source: source.to_string(), regex }
+    }
+
+    /// A regular expression that must match somewhere in the string.
+    pub fn regex(source: &str) -> Result<Pattern, regex::Error> {
+        Regex::new(source).map(|regex| Pattern { source: source.to_string(), regex })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regex.is_match(text)
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+/// The reason why a query is invalid along with the byte range of the offending token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    pub span: Range<usize>,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.span.start)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    LParen,
+    RParen,
+    Op(Op),
+    String(String),
+    Number(f64),
+    /// A field name or a keyword like `and`, `or`, `not`, `tagged`, `true` or `nil`.
+    Ident(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Range<usize>,
+}
+
+pub const FIELDS: &[&str] = &["host", "service", "state", "description", "metric", "time", "ttl"];
+
+pub const KEYWORDS: &[&str] = &["and", "or", "not", "tagged", "true", "false", "nil", "null"];
+
+/// Quotes a string the way the tokenizer reads it back.
+pub fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+pub fn parse(query: &str) -> Result<Query, ParseError> {
+    let tokens = tokenize(query)?;
+    let mut parser = Parser { tokens: &tokens, position: 0, end: query.len() };
+    if tokens.is_empty() {
+        return Err(ParseError { message: "empty query".to_string(), span: 0..query.len() });
+    }
+
+    let query = parser.or()?;
+    match parser.peek() {
+        Some(token) => Err(parser.unexpected(token)),
+        None => Ok(query),
+    }
+}
+
+/// Splits the query into tokens, an error is returned for unterminated strings or unknown characters.
+pub fn tokenize(query: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = query.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let kind = match c {
+            c if c.is_whitespace() => continue,
+            '(' => TokenKind::LParen,
+            ')' => TokenKind::RParen,
+            '<' | '>' | '!' | '=' | '~' => {
+                let next = chars.peek().map(|(_, c)| *c);
+                let (op, double) = match (c, next) {
+                    ('<', Some('=')) => (Some(Op::LessEqual), true),
+                    ('<', _) => (Some(Op::Less), false),
+                    ('>', Some('=')) => (Some(Op::GreaterEqual), true),
+                    ('>', _) => (Some(Op::Greater), false),
+                    ('!', Some('=')) => (Some(Op::NotEqual), true),
+                    ('=', Some('=')) => (Some(Op::Equal), true),
+                    ('=', Some('~')) => (Some(Op::Like), true),
+                    ('=', _) => (Some(Op::Equal), false),
+                    ('~', Some('=')) => (Some(Op::Regex), true),
+                    _ => (None, false),
+                };
+                if double {
+                    chars.next();
+                }
+                match op {
+                    Some(op) => TokenKind::Op(op),
+                    None => {
+                        let span = start..start + c.len_utf8();
+                        let message = format!("unexpected character {:?}", c);
+                        return Err(ParseError { message, span });
+                    }
+                }
+            }
+            '"' => {
+                let mut string = String::new();
+                let mut terminated = false;
+                while let Some((_, c)) = chars.next() {
+                    match c {
+                        '"' => {
+                            terminated = true;
+                            break;
+                        }
+                        '\\' => match chars.next() {
+                            Some((_, 'n')) => string.push('\n'),
+                            Some((_, 't')) => string.push('\t'),
+                            Some((_, 'r')) => string.push('\r'),
+                            Some((_, c)) => string.push(c),
+                            None => break,
+                        },
+                        c => string.push(c),
+                    }
+                }
+                if !terminated {
+                    let message = "unterminated string".to_string();
+                    return Err(ParseError { message, span: start..query.len() });
+                }
+                TokenKind::String(string)
+            }
+            c if c == '-' || c.is_ascii_digit() => {
+                let end = take_while(&mut chars, query.len(), |c| {
+                    c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+')
+                });
+                let number = &query[start..end];
+                match number.parse() {
+                    Ok(number) => TokenKind::Number(number),
+                    Err(_) => {
+                        let message = format!("invalid number {:?}", number);
+                        return Err(ParseError { message, span: start..end });
+                    }
+                }
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let end = take_while(&mut chars, query.len(), |c| c.is_alphanumeric() || c == '_');
+                TokenKind::Ident(query[start..end].to_string())
+            }
+            c => {
+                let span = start..start + c.len_utf8();
+                return Err(ParseError { message: format!("unexpected character {:?}", c), span });
+            }
+        };
+
+        let end = chars.peek().map_or(query.len(), |(i, _)| *i);
+        tokens.push(Token { kind, span: start..end });
+    }
+
+    Ok(tokens)
+}
+
+/// Consumes the chars matching the predicate and returns the end of the consumed ones.
+fn take_while(
+    chars: &mut std::iter::Peekable<std::str::CharIndices<'_>>,
+    len: usize,
+    predicate: impl Fn(char) -> bool,
+) -> usize {
+    while chars.next_if(|(_, c)| predicate(*c)).is_some() {}
+    chars.peek().map_or(len, |(i, _)| *i)
+}
+
+struct Parser<'t> {
+    tokens: &'t [Token],
+    position: usize,
+    /// The length of the query, used for errors at the end of it.
+    end: usize,
+}
+
+impl<'t> Parser<'t> {
+    fn peek(&self) -> Option<&'t Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&'t Token> {
+        let token = self.tokens.get(self.position);
+        self.position += 1;
+        token
+    }
+
+    fn next_is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token { kind: TokenKind::Ident(ident), .. }) if ident == keyword)
+    }
+
+    fn unexpected(&self, token: &Token) -> ParseError {
+        let message = match &token.kind {
+            TokenKind::LParen => "unexpected \"(\"".to_string(),
+            TokenKind::RParen => "unexpected \")\"".to_string(),
+            TokenKind::Op(_) => "unexpected operator".to_string(),
+            TokenKind::String(s) => format!("unexpected string {:?}", s),
+            TokenKind::Number(n) => format!("unexpected number {}", n),
+            TokenKind::Ident(ident) => format!("unexpected {:?}", ident),
+        };
+        ParseError { message, span: token.span.clone() }
+    }
+
+    fn end_of_query(&self, expected: &str) -> ParseError {
+        let message = format!("expected {} but the query ended", expected);
+        ParseError { message, span: self.end..self.end }
+    }
+
+    fn or(&mut self) -> Result<Query, ParseError> {
+        let mut queries = vec![self.and()?];
+        while self.next_is_keyword("or") {
+            self.next();
+            queries.push(self.and()?);
+        }
+        Ok(if queries.len() == 1 { queries.pop().unwrap() } else { Query::Or(queries) })
+    }
+
+    fn and(&mut self) -> Result<Query, ParseError> {
+        let mut queries = vec![self.not()?];
+        while self.next_is_keyword("and") {
+            self.next();
+            queries.push(self.not()?);
+        }
+        Ok(if queries.len() == 1 { queries.pop().unwrap() } else { Query::And(queries) })
+    }
+
+    fn not(&mut self) -> Result<Query, ParseError> {
+        if self.next_is_keyword("not") {
+            self.next();
+            Ok(Query::Not(Box::new(self.not()?)))
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<Query, ParseError> {
+        let token = self.next().ok_or_else(|| self.end_of_query("a predicate"))?;
+        match &token.kind {
+            TokenKind::LParen => {
+                let query = self.or()?;
+                match self.next() {
+                    Some(Token { kind: TokenKind::RParen, .. }) => Ok(query),
+                    Some(token) => Err(self.unexpected(token)),
+                    None => Err(self.end_of_query("\")\"")),
+                }
+            }
+            TokenKind::Ident(ident) => match ident.as_str() {
+                "true" => Ok(Query::True),
+                "false" => Ok(Query::False),
+                "tagged" => match self.next() {
+                    Some(Token { kind: TokenKind::String(tag), .. }) => {
+                        Ok(Query::Tagged(tag.clone()))
+                    }
+                    Some(token) => Err(ParseError {
+                        message: "expected a quoted tag".to_string(),
+                        span: token.span.clone(),
+                    }),
+                    None => Err(self.end_of_query("a quoted tag")),
+                },
+                ident if KEYWORDS.contains(&ident) => Err(self.unexpected(token)),
+                ident => self.predicate(Field::from_name(ident)),
+            },
+            TokenKind::String(name) => self.predicate(Field::from_name(name)),
+            _ => Err(self.unexpected(token)),
+        }
+    }
+
+    fn predicate(&mut self, field: Field) -> Result<Query, ParseError> {
+        let op = match self.next() {
+            Some(Token { kind: TokenKind::Op(op), .. }) => *op,
+            Some(token) => {
+                let message = "expected an operator like = or =~".to_string();
+                return Err(ParseError { message, span: token.span.clone() });
+            }
+            None => return Err(self.end_of_query("an operator")),
+        };
+
+        let token = self.next().ok_or_else(|| self.end_of_query("a value"))?;
+        let value = match &token.kind {
+            TokenKind::String(s) => Value::String(s.clone()),
+            TokenKind::Number(n) => Value::Number(*n),
+            TokenKind::Ident(ident) => match ident.as_str() {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                "nil" | "null" => Value::Nil,
+                _ => {
+                    let message = "expected a value, strings must be quoted".to_string();
+                    return Err(ParseError { message, span: token.span.clone() });
+                }
+            },
+            _ => return Err(self.unexpected(token)),
+        };
+
+        let value = match (op, value) {
+            (Op::Like, Value::String(s)) => Value::Pattern(Pattern::like(&s)),
+            (Op::Regex, Value::String(s)) => match Pattern::regex(&s) {
+                Ok(pattern) => Value::Pattern(pattern),
+                Err(e) => {
+                    let message = format!("invalid regex: {}", e);
+                    return Err(ParseError { message, span: token.span.clone() });
+                }
+            },
+            (Op::Like | Op::Regex, _) => {
+                let message = "expected a quoted pattern".to_string();
+                return Err(ParseError { message, span: token.span.clone() });
+            }
+            (_, value) => value,
+        };
+
+        Ok(Query::Compare { field, op, value })
+    }
+}
+
+impl Field {
+    pub fn from_name(name: &str) -> Field {
+        match name {
+            "host" => Field::Host,
+            "service" => Field::Service,
+            "state" => Field::State,
+            "description" => Field::Description,
+            "metric" | "metric_f" => Field::Metric,
+            "time" => Field::Time,
+            "ttl" => Field::Ttl,
+            other => Field::Custom(other.to_string()),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Field::Host => "host",
+            Field::Service => "service",
+            Field::State => "state",
+            Field::Description => "description",
+            Field::Metric => "metric",
+            Field::Time => "time",
+            Field::Ttl => "ttl",
+            Field::Custom(name) => name,
+        }
+    }
+}
+
+/// The value of a field of an event, as seen by a query.
+#[derive(Debug, Clone, Copy)]
+enum FieldValue<'a> {
+    Nil,
+    String(&'a str),
+    Number(f64),
+    Bool(bool),
+}
+
+impl Query {
+    /// Whether the event matches this query, following the semantics of the Riemann server.
+    pub fn matches(&self, event: &Event) -> bool {
+        match self {
+            Query::True => true,
+            Query::False => false,
+            Query::Tagged(tag) => event.tags.contains(tag),
+            Query::Compare { field, op, value } => compare(field.value_of(event), *op, value),
+            Query::Not(query) => !query.matches(event),
+            Query::And(queries) => queries.iter().all(|q| q.matches(event)),
+            Query::Or(queries) => queries.iter().any(|q| q.matches(event)),
+        }
+    }
+}
+
+impl Field {
+    fn value_of<'a>(&self, event: &'a Event) -> FieldValue<'a> {
+        let string =
+            |s: &'a Option<String>| s.as_deref().map_or(FieldValue::Nil, FieldValue::String);
+        let number = |n: Option<f64>| n.map_or(FieldValue::Nil, FieldValue::Number);
+        match self {
+            Field::Host => string(&event.host),
+            Field::Service => string(&event.service),
+            Field::State => string(&event.state),
+            Field::Description => string(&event.description),
+            Field::Metric => number(event.metric.map(f64::from)),
+            // the server compares times in seconds since the epoch
+            Field::Time => number(event.time.map(|t| t.unix_timestamp_nanos() as f64 / 1e9)),
+            Field::Ttl => number(event.ttl.map(f64::from)),
+            Field::Custom(name) => match event.attributes.get(name) {
+                Some(serde_json::Value::String(s)) => FieldValue::String(s),
+                Some(serde_json::Value::Number(n)) => number(n.as_f64()),
+                Some(serde_json::Value::Bool(b)) => FieldValue::Bool(*b),
+                _ => FieldValue::Nil,
+            },
+        }
+    }
+}
+
+fn compare(field: FieldValue<'_>, op: Op, value: &Value) -> bool {
+    let ordering = match (field, value) {
+        (FieldValue::Number(a), Value::Number(b)) => a.partial_cmp(b),
+        (FieldValue::String(a), Value::String(b)) => Some(a.cmp(b.as_str())),
+        _ => None,
+    };
+
+    match op {
+        Op::Equal => equals(field, value),
+        Op::NotEqual => !equals(field, value),
+        Op::Less => ordering == Some(Ordering::Less),
+        Op::LessEqual => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+        Op::Greater => ordering == Some(Ordering::Greater),
+        Op::GreaterEqual => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+        Op::Like | Op::Regex => match (field, value) {
+            (FieldValue::String(text), Value::Pattern(pattern)) => pattern.is_match(text),
+            _ => false,
+        },
+    }
+}
+
+fn equals(field: FieldValue<'_>, value: &Value) -> bool {
+    match (field, value) {
+        (FieldValue::Nil, Value::Nil) => true,
+        (FieldValue::String(a), Value::String(b)) => a == b,
+        (FieldValue::Number(a), Value::Number(b)) => a == *b,
+        (FieldValue::Bool(a), Value::Bool(b)) => a == *b,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compare(field: Field, op: Op, value: Value) -> Query {
+        Query::Compare { field, op, value }
+    }
+
+    fn host(name: &str) -> Query {
+        compare(Field::Host, Op::Equal, Value::String(name.to_string()))
+    }
+
+    fn event(json: serde_json::Value) -> Event {
+        let mut event = serde_json::json!({ "time": null, "tags": [] });
+        event.as_object_mut().unwrap().extend(json.as_object().unwrap().clone());
+        serde_json::from_value(event).unwrap()
+    }
+
+    fn matches(query: &str, event: &Event) -> bool {
+        parse(query).unwrap().matches(event)
+    }
+
+    fn error_span(query: &str) -> Range<usize> {
+        parse(query).unwrap_err().span
+    }
+
+    #[test]
+    fn precedence() {
+        let query = parse(r#"not host = "a" and host = "b" or host = "c""#).unwrap();
+        let not_a = Query::Not(Box::new(host("a")));
+        assert_eq!(query, Query::Or(vec![Query::And(vec![not_a, host("b")]), host("c")]));
+
+        let query = parse(r#"host = "a" or host = "b" and not host = "c""#).unwrap();
+        let not_c = Query::Not(Box::new(host("c")));
+        assert_eq!(query, Query::Or(vec![host("a"), Query::And(vec![host("b"), not_c])]));
+
+        let query = parse(r#"not (host = "a" or host = "b")"#).unwrap();
+        assert_eq!(query, Query::Not(Box::new(Query::Or(vec![host("a"), host("b")]))));
+    }
+
+    #[test]
+    fn patterns() {
+        let query = parse(r#"service =~ "cpu%""#).unwrap();
+        assert_eq!(query, compare(Field::Service, Op::Like, Value::Pattern(Pattern::like("cpu%"))));
+
+        let query = parse(r#"service ~= "^cpu-\\d+$""#).unwrap();
+        let pattern = Pattern::regex(r"^cpu-\d+$").unwrap();
+        assert_eq!(query, compare(Field::Service, Op::Regex, Value::Pattern(pattern)));
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(parse(r#"tagged "x""#).unwrap(), Query::Tagged("x".to_string()));
+        assert_eq!(parse("state = nil").unwrap(), compare(Field::State, Op::Equal, Value::Nil));
+        assert_eq!(
+            parse("state != null").unwrap(),
+            compare(Field::State, Op::NotEqual, Value::Nil)
+        );
+        assert_eq!(
+            parse("metric > -1.5").unwrap(),
+            compare(Field::Metric, Op::Greater, Value::Number(-1.5))
+        );
+        assert_eq!(
+            parse("metric<=2e3").unwrap(),
+            compare(Field::Metric, Op::LessEqual, Value::Number(2000.0))
+        );
+        assert_eq!(
+            parse(r#""disk usage" >= 0.25"#).unwrap(),
+            compare(Field::Custom("disk usage".to_string()), Op::GreaterEqual, Value::Number(0.25))
+        );
+        assert_eq!(parse("true").unwrap(), Query::True);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(error_span(r#"service = "cpu"#), 10..14);
+        assert_eq!(error_span("service ="), 9..9);
+        assert_eq!(error_span(r#"host = "a" and"#), 14..14);
+        assert_eq!(error_span(r#"host = "a" ="#), 11..12);
+        assert_eq!(error_span(r#"(host = "a""#), 11..11);
+        assert_eq!(error_span(r#"host = "a")"#), 10..11);
+        assert_eq!(error_span("host = foo"), 7..10);
+        assert_eq!(error_span(r#"tagged x"#), 7..8);
+        assert_eq!(error_span(r#"service ~= "(""#), 11..14);
+        assert_eq!(error_span(""), 0..0);
+    }
+
+    #[test]
+    fn nil_fields() {
+        let event = event(serde_json::json!({ "service": "cpu" }));
+        assert!(!matches(r#"host = "a""#, &event));
+        assert!(matches(r#"host != "a""#, &event));
+        assert!(matches("host = nil", &event));
+        assert!(!matches("service = nil", &event));
+        assert!(!matches(r#"host =~ "%""#, &event));
+        assert!(!matches("metric > 1", &event));
+        assert!(!matches("metric <= 1", &event));
+    }
+
+    #[test]
+    fn not_missing_metric() {
+        let event = event(serde_json::json!({}));
+        assert!(matches("not metric > 1", &event));
+        assert!(matches("not metric <= 1", &event));
+        assert!(!matches("not metric = nil", &event));
+    }
+
+    #[test]
+    fn like_matches_whole_string() {
+        let event = event(serde_json::json!({ "service": "cpu user" }));
+        assert!(matches(r#"service =~ "cpu%""#, &event));
+        assert!(matches(r#"service =~ "%user""#, &event));
+        assert!(matches(r#"service =~ "cpu user""#, &event));
+        assert!(!matches(r#"service =~ "cpu""#, &event));
+        assert!(!matches(r#"service =~ "user%""#, &event));
+        assert!(!matches(r#"service =~ "cpu.user""#, &event));
+        assert!(matches(r#"service ~= "pu u""#, &event));
+    }
+
+    #[test]
+    fn custom_attributes() {
+        let event = event(serde_json::json!({ "version": "2", "count": 3, "canary": true }));
+        assert!(matches(r#"version = "2""#, &event));
+        assert!(!matches("version = 2", &event));
+        assert!(matches("count = 3", &event));
+        assert!(matches("count > 2.5", &event));
+        assert!(!matches(r#"count = "3""#, &event));
+        assert!(matches("canary = true", &event));
+        assert!(matches("missing = nil", &event));
+    }
+
+    #[test]
+    fn time_in_seconds() {
+        let event = event(serde_json::json!({ "time": "2026-01-01T00:00:00.5Z" }));
+        assert!(matches("time > 1767225600", &event));
+        assert!(matches("time < 1767225601", &event));
+        assert!(matches("time = 1767225600.5", &event));
+        assert!(!matches("time > 1767225600000", &event));
+    }
+}
@@ -0,0 +1,387 @@
+use eframe::egui;
+use eframe::egui::{Color32, RichText};
+
+use crate::query::{self, Field, Op, Query, Value};
+
+/// The name of the pseudo field of the `tagged "..."` conditions.
+const TAGGED: &str = "tagged";
+
+/// A form describing a query as groups of conditions, for those who don't
+/// remember the query syntax. Only simple queries can be turned back into a form.
+pub struct QueryBuilder {
+    join: Join,
+    groups: Vec<Group>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Join {
+    And,
+    Or,
+}
+
+struct Group {
+    join: Join,
+    conditions: Vec<Condition>,
+}
+
+struct Condition {
+    /// A field name or [`TAGGED`].
+    field: String,
+    op: Op,
+    value: String,
+    kind: ValueKind,
+}
+
+/// How the value of a condition is written in the query, a number
+/// never matches a string attribute and the other way around.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ValueKind {
+    String,
+    Number,
+}
+
+impl ValueKind {
+    /// The kind of the values of this field when it is picked in the form.
+    fn of_field(field: &str) -> ValueKind {
+        match Field::from_name(field) {
+            Field::Metric | Field::Time | Field::Ttl => ValueKind::Number,
+            _ => ValueKind::String,
+        }
+    }
+}
+
+impl QueryBuilder {
+    /// Turns a query into a form, returns `None` if it is too complex for the form.
+    pub fn from_query(query: &Query) -> Option<QueryBuilder> {
+        let (join, groups) = match query {
+            Query::And(queries) => (Join::And, groups(queries, Join::Or)?),
+            Query::Or(queries) => (Join::Or, groups(queries, Join::And)?),
+            query => {
+                let conditions = vec![Condition::from_query(query)?];
+                (Join::And, vec![Group { join: Join::And, conditions }])
+            }
+        };
+        Some(QueryBuilder { join, groups })
+    }
+
+    /// The query string described by the form.
+    pub fn to_query_string(&self) -> String {
+        let groups: Vec<_> = self
+            .groups
+            .iter()
+            .filter(|g| !g.conditions.is_empty())
+            .map(|g| {
+                let conditions: Vec<_> =
+                    g.conditions.iter().map(Condition::to_query_string).collect();
+                match conditions.as_slice() {
+                    [condition] => condition.clone(),
+                    _ if self.groups.len() == 1 => conditions.join(g.join.separator()),
+                    _ => format!("({})", conditions.join(g.join.separator())),
+                }
+            })
+            .collect();
+
+        match groups.as_slice() {
+            [] => "true".to_string(),
+            _ => groups.join(self.join.separator()),
+        }
+    }
+
+    /// Displays the form, returns `true` when the query must be applied.
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        ui.horizontal(|ui| {
+            ui.label("match");
+            join_ui(ui, "join", &mut self.join);
+            ui.label("of the groups");
+        });
+
+        let mut delete = None;
+        for (i, group) in self.groups.iter_mut().enumerate() {
+            ui.push_id(i, |ui| {
+                ui.group(|ui| {
+                    if group.ui(ui) {
+                        delete = Some(i);
+                    }
+                });
+            });
+        }
+        if let Some(i) = delete {
+            self.groups.remove(i);
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("+ group").clicked() {
+                self.groups.push(Group::default());
+            }
+            ui.button("Apply").on_hover_text(self.to_query_string()).clicked()
+        })
+        .inner
+    }
+}
+
+impl Default for QueryBuilder {
+    fn default() -> Self {
+        QueryBuilder { join: Join::Or, groups: vec![Group::default()] }
+    }
+}
+
+fn groups(queries: &[Query], inner: Join) -> Option<Vec<Group>> {
+    queries
+        .iter()
+        .map(|query| {
+            let conditions = match (query, inner) {
+                (Query::And(queries), Join::And) | (Query::Or(queries), Join::Or) => {
+                    queries.iter().map(Condition::from_query).collect::<Option<_>>()?
+                }
+                (query, _) => vec![Condition::from_query(query)?],
+            };
+            Some(Group { join: inner, conditions })
+        })
+        .collect()
+}
+
+impl Join {
+    fn separator(&self) -> &'static str {
+        match self {
+            Join::And => " and ",
+            Join::Or => " or ",
+        }
+    }
+}
+
+fn join_ui(ui: &mut egui::Ui, id_source: &str, join: &mut Join) {
+    egui::ComboBox::from_id_source(id_source)
+        .width(50.0)
+        .selected_text(if *join == Join::And { "all" } else { "any" })
+        .show_ui(ui, |ui| {
+            ui.selectable_value(join, Join::And, "all");
+            ui.selectable_value(join, Join::Or, "any");
+        });
+}
+
+impl Group {
+    /// Displays the conditions of the group, returns `true` if the group must be deleted.
+    fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut delete_group = false;
+        ui.horizontal(|ui| {
+            ui.label("match");
+            join_ui(ui, "join", &mut self.join);
+            ui.label("of");
+            delete_group = ui.small_button("🗑").on_hover_text("Delete the group").clicked();
+        });
+
+        let mut delete = None;
+        for (i, condition) in self.conditions.iter_mut().enumerate() {
+            ui.push_id(i, |ui| {
+                if condition.ui(ui) {
+                    delete = Some(i);
+                }
+            });
+        }
+        if let Some(i) = delete {
+            self.conditions.remove(i);
+        }
+
+        if ui.button("+ condition").clicked() {
+            self.conditions.push(Condition::default());
+        }
+
+        delete_group
+    }
+}
+
+impl Default for Group {
+    fn default() -> Self {
+        Group { join: Join::And, conditions: vec![Condition::default()] }
+    }
+}
+
+impl Condition {
+    fn from_query(query: &Query) -> Option<Condition> {
+        match query {
+            Query::Tagged(tag) => Some(Condition {
+                field: TAGGED.to_string(),
+                op: Op::Equal,
+                value: tag.clone(),
+                kind: ValueKind::String,
+            }),
+            Query::Compare { field, op, value } => {
+                let (value, kind) = match value {
+                    Value::String(s) => (s.clone(), ValueKind::String),
+                    Value::Number(n) => (n.to_string(), ValueKind::Number),
+                    Value::Pattern(pattern) => (pattern.source().to_string(), ValueKind::String),
+                    Value::Bool(_) | Value::Nil => return None,
+                };
+                Some(Condition { field: field.name().to_string(), op: *op, value, kind })
+            }
+            _ => None,
+        }
+    }
+
+    fn to_query_string(&self) -> String {
+        if self.field == TAGGED {
+            return format!("tagged {}", query::quote(&self.value));
+        }
+
+        let field = match Field::from_name(&self.field) {
+            // the names that are not identifiers are quoted
+            Field::Custom(name) if !is_ident(&name) => query::quote(&name),
+            field => field.name().to_string(),
+        };
+        let value = match self.kind {
+            ValueKind::Number if !self.is_pattern() => match self.value.trim().parse::<f64>() {
+                Ok(n) if n.is_finite() => n.to_string(),
+                // written as is to report the invalid number
+                _ => self.value.trim().to_string(),
+            },
+            _ => query::quote(&self.value),
+        };
+        format!("{} {} {}", field, self.op.symbol(), value)
+    }
+
+    fn is_pattern(&self) -> bool {
+        matches!(self.op, Op::Like | Op::Regex)
+    }
+
+    /// Displays the condition on a line, returns `true` if it must be deleted.
+    fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        ui.horizontal(|ui| {
+            let known = self.field == TAGGED || query::FIELDS.contains(&self.field.as_str());
+            egui::ComboBox::from_id_source("field")
+                .width(90.0)
+                .selected_text(if known { self.field.as_str() } else { "custom" })
+                .show_ui(ui, |ui| {
+                    for field in query::FIELDS.iter().chain(&[TAGGED]) {
+                        if ui.selectable_label(self.field == *field, *field).clicked() {
+                            self.field = field.to_string();
+                            self.kind = ValueKind::of_field(field);
+                        }
+                    }
+                    if ui.selectable_label(!known, "custom").clicked() && known {
+                        self.field.clear();
+                        self.kind = ValueKind::String;
+                    }
+                });
+            if !known {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.field)
+                        .hint_text("attribute")
+                        .desired_width(80.0),
+                );
+            }
+
+            if self.field != TAGGED {
+                egui::ComboBox::from_id_source("op")
+                    .width(40.0)
+                    .selected_text(self.op.symbol())
+                    .show_ui(ui, |ui| {
+                        for op in Op::ALL {
+                            ui.selectable_value(&mut self.op, *op, op.symbol());
+                        }
+                    });
+            }
+
+            let value_edit = egui::TextEdit::singleline(&mut self.value).desired_width(120.0);
+            let value_edit = match self.op {
+                Op::Like if self.field != TAGGED => value_edit.hint_text("pattern%"),
+                Op::Regex if self.field != TAGGED => value_edit.hint_text("regex"),
+                _ => value_edit,
+            };
+            ui.add(value_edit);
+            if self.field != TAGGED && !self.is_pattern() {
+                let number = self.kind == ValueKind::Number;
+                let response = ui.selectable_label(number, "#").on_hover_text(match number {
+                    true => "compared as a number",
+                    false => "compared as a string",
+                });
+                if response.clicked() {
+                    self.kind = if number { ValueKind::String } else { ValueKind::Number };
+                }
+            }
+
+            if let Err(e) = query::parse(&self.to_query_string()) {
+                ui.label(RichText::new("⚠").color(Color32::LIGHT_RED)).on_hover_text(e.message);
+            }
+
+            ui.small_button("🗑").on_hover_text("Delete the condition").clicked()
+        })
+        .inner
+    }
+}
+
+impl Default for Condition {
+    fn default() -> Self {
+        Condition {
+            field: "service".to_string(),
+            op: Op::Equal,
+            value: String::new(),
+            kind: ValueKind::String,
+        }
+    }
+}
+
+fn is_ident(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+        && !query::KEYWORDS.contains(&name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses the query, turns it into a form and parses the query string of the form.
+    fn round_trip(query: &str) -> (Query, Query) {
+        let original = query::parse(query).unwrap();
+        let builder = QueryBuilder::from_query(&original).unwrap();
+        let query_string = builder.to_query_string();
+        (original, query::parse(&query_string).unwrap())
+    }
+
+    #[test]
+    fn round_trips() {
+        for query in [
+            r#"service = "cpu""#,
+            r#"version = "2""#,
+            "version = 2",
+            "metric > -1.5",
+            "ttl <= 0.25",
+            r#"host = "inf""#,
+            r#"state = "nan""#,
+            r#"build = "1e3""#,
+            r#""disk usage" >= 90"#,
+            r#"service =~ "cpu%""#,
+            r#"service ~= "^cpu-\\d+$""#,
+            r#"tagged "prod""#,
+            r#"host = "a" and service = "b""#,
+            r#"host = "a" or host = "b" and tagged "x""#,
+            r#"(host = "a" or host = "b") and (service = "c" or service = "d")"#,
+        ] {
+            let (original, round_tripped) = round_trip(query);
+            assert_eq!(original, round_tripped, "{}", query);
+        }
+    }
+
+    #[test]
+    fn complex_queries() {
+        for query in [
+            "not host = nil",
+            "canary = true",
+            r#"host = "a" and (tagged "b" or tagged "c" and tagged "d")"#,
+        ] {
+            assert!(QueryBuilder::from_query(&query::parse(query).unwrap()).is_none(), "{}", query);
+        }
+    }
+
+    #[test]
+    fn invalid_numbers() {
+        let condition = Condition {
+            field: "metric".to_string(),
+            op: Op::Equal,
+            value: "inf".to_string(),
+            kind: ValueKind::Number,
+        };
+        assert!(query::parse(&condition.to_query_string()).is_err());
+    }
+}
@@ -0,0 +1,286 @@
+use std::collections::VecDeque;
+use std::hash::Hash;
+use std::ops::Range;
+use std::sync::Arc;
+
+use eframe::egui;
+use eframe::egui::text::{CCursor, CCursorRange, LayoutJob, TextFormat};
+use eframe::egui::widgets::text_edit::TextEditState;
+use eframe::egui::{Color32, Galley, Key, Modifiers, RichText, Stroke, TextStyle};
+
+use crate::event::Event;
+use crate::query::{self, Op, TokenKind};
+
+/// The maximum number of host and service values remembered for the completion.
+const MAX_RECENT_VALUES: usize = 256;
+/// The maximum number of suggestions displayed under the editor.
+const MAX_SUGGESTIONS: usize = 8;
+
+/// A code editor for queries with syntax highlighting and completion of
+/// the field names and of the recently received host and service values.
+///
+/// The first suggestion is accepted with the Tab key.
+pub struct QueryEditor<'a> {
+    id_source: egui::Id,
+    query: &'a mut String,
+    completions: &'a Completions,
+    multiline: bool,
+}
+
+impl<'a> QueryEditor<'a> {
+    pub fn new(id_source: impl Hash, query: &'a mut String, completions: &'a Completions) -> Self {
+        QueryEditor { id_source: egui::Id::new(id_source), query, completions, multiline: false }
+    }
+
+    pub fn multiline(self, multiline: bool) -> Self {
+        Self { multiline, ..self }
+    }
+}
+
+impl egui::Widget for QueryEditor<'_> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let id = ui.make_persistent_id(self.id_source);
+
+        // the suggestions of the previous frame are the ones the user saw
+        let mut completed = false;
+        if ui.memory().has_focus(id) {
+            let mut state = TextEditState::load(ui.ctx(), id).unwrap_or_default();
+            let cursor = state.ccursor_range().map(|range| range.primary.index);
+            let completion = cursor.and_then(|c| complete(self.query, c, self.completions));
+            if let Some((range, suggestions)) = completion {
+                if ui.input_mut().consume_key(Modifiers::NONE, Key::Tab) {
+                    self.query.replace_range(range.clone(), &suggestions[0]);
+                    let cursor =
+                        self.query[..range.start].chars().count() + suggestions[0].chars().count();
+                    state.set_ccursor_range(Some(CCursorRange::one(CCursor::new(cursor))));
+                    state.store(ui.ctx(), id);
+                    completed = true;
+                }
+            }
+        }
+
+        let mut layouter = layout;
+        let text_edit = if self.multiline {
+            egui::TextEdit::multiline(self.query).desired_rows(3)
+        } else {
+            egui::TextEdit::singleline(self.query)
+        };
+        let output = text_edit
+            .id(id)
+            .font(TextStyle::Monospace) // for cursor height
+            .code_editor()
+            .lock_focus(true) // the Tab key accepts the completion
+            .desired_width(f32::INFINITY)
+            .layouter(&mut layouter)
+            .show(ui);
+
+        let mut response = output.response;
+        if completed {
+            response.mark_changed();
+        }
+
+        if response.has_focus() {
+            let cursor = output.cursor_range.map(|range| range.as_ccursor_range().primary.index);
+            if let Some((_, suggestions)) =
+                cursor.and_then(|c| complete(self.query, c, self.completions))
+            {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(RichText::new("⇥").weak());
+                    for suggestion in suggestions {
+                        ui.label(RichText::new(suggestion).monospace().weak());
+                    }
+                });
+            }
+        }
+
+        response
+    }
+}
+
+/// The host and service values of the recently received events.
+#[derive(Default)]
+pub struct Completions {
+    hosts: RecentValues,
+    services: RecentValues,
+}
+
+impl Completions {
+    pub fn observe(&mut self, event: &Event) {
+        if let Some(host) = &event.host {
+            self.hosts.insert(host);
+        }
+        if let Some(service) = &event.service {
+            self.services.insert(service);
+        }
+    }
+
+    fn values(&self, field: &str) -> Option<&RecentValues> {
+        match field {
+            "host" => Some(&self.hosts),
+            "service" => Some(&self.services),
+            _ => None,
+        }
+    }
+}
+
+/// Unique values, the most recent first.
+#[derive(Default)]
+struct RecentValues(VecDeque<String>);
+
+impl RecentValues {
+    fn insert(&mut self, value: &str) {
+        if self.0.front().map(String::as_str) == Some(value) {
+            return;
+        }
+        match self.0.iter().position(|v| v == value) {
+            Some(i) => {
+                let value = self.0.remove(i).unwrap();
+                self.0.push_front(value);
+            }
+            None => {
+                self.0.push_front(value.to_string());
+                self.0.truncate(MAX_RECENT_VALUES);
+            }
+        }
+    }
+}
+
+/// Returns the byte range to replace and the replacements for the word before the cursor,
+/// `cursor` is a character offset.
+fn complete(
+    query: &str,
+    cursor: usize,
+    completions: &Completions,
+) -> Option<(Range<usize>, Vec<String>)> {
+    let cursor = query.char_indices().nth(cursor).map_or(query.len(), |(i, _)| i);
+    let before = &query[..cursor];
+
+    let (range, prefix, values) = match query::tokenize(before) {
+        // the cursor is in a string, it can only be a value
+        Err(e) if before[e.span.start..].starts_with('"') => {
+            let start = e.span.start;
+            let end = if query[cursor..].starts_with('"') { cursor + 1 } else { cursor };
+            let values = compared_field(&before[..start]).and_then(|f| completions.values(f))?;
+            (start..end, &before[start + 1..], Some(values))
+        }
+        Err(_) => return None,
+        Ok(tokens) => match tokens.last() {
+            Some(token)
+                if token.span.end == cursor && matches!(token.kind, TokenKind::Ident(_)) =>
+            {
+                (token.span.clone(), &before[token.span.clone()], None)
+            }
+            _ if !before.ends_with(char::is_alphanumeric) => {
+                let values = compared_field(before).and_then(|f| completions.values(f))?;
+                (cursor..cursor, "", Some(values))
+            }
+            _ => return None,
+        },
+    };
+
+    let suggestions: Vec<_> = match values {
+        Some(values) => values
+            .0
+            .iter()
+            .filter(|v| v.starts_with(prefix) && v.as_str() != prefix)
+            .map(|v| query::quote(v))
+            .take(MAX_SUGGESTIONS)
+            .collect(),
+        None => query::FIELDS
+            .iter()
+            .chain(query::KEYWORDS)
+            .filter(|w| w.starts_with(prefix) && **w != prefix)
+            .map(|w| w.to_string())
+            .take(MAX_SUGGESTIONS)
+            .collect(),
+    };
+
+    if suggestions.is_empty() {
+        None
+    } else {
+        Some((range, suggestions))
+    }
+}
+
+/// The name of the field compared at the end of this query, like `host` in `host = `.
+fn compared_field(query: &str) -> Option<&str> {
+    let tokens = query::tokenize(query).ok()?;
+    match tokens.as_slice() {
+        [.., field, op] => match (&field.kind, &op.kind) {
+            (TokenKind::Ident(_), TokenKind::Op(Op::Equal | Op::NotEqual | Op::Like)) => {
+                Some(&query[field.span.clone()])
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Class {
+    Normal,
+    Keyword,
+    Field,
+    String,
+    Number,
+    Error,
+}
+
+/// Lays out a query for a code editor, the keywords, fields, strings and numbers
+/// are highlighted and the token where the parsing failed is underlined.
+pub fn layout(ui: &egui::Ui, text: &str, wrap_width: f32) -> Arc<Galley> {
+    let font_id = TextStyle::Monospace.resolve(ui.style());
+    let normal = TextFormat::simple(font_id, ui.visuals().widgets.inactive.text_color());
+    let colored = |color| TextFormat { color, ..normal.clone() };
+    let dark_mode = ui.visuals().dark_mode;
+    let format = |class| match (class, dark_mode) {
+        (Class::Normal, _) => normal.clone(),
+        (Class::Keyword, true) => colored(Color32::from_rgb(0xc6, 0x78, 0xdd)),
+        (Class::Keyword, false) => colored(Color32::from_rgb(0xa6, 0x26, 0xa4)),
+        (Class::Field, true) => colored(Color32::from_rgb(0x61, 0xaf, 0xef)),
+        (Class::Field, false) => colored(Color32::from_rgb(0x40, 0x78, 0xf2)),
+        (Class::String, true) => colored(Color32::from_rgb(0x98, 0xc3, 0x79)),
+        (Class::String, false) => colored(Color32::from_rgb(0x50, 0xa1, 0x4f)),
+        (Class::Number, true) => colored(Color32::from_rgb(0xd1, 0x9a, 0x66)),
+        (Class::Number, false) => colored(Color32::from_rgb(0x98, 0x68, 0x01)),
+        (Class::Error, _) => TextFormat {
+            color: Color32::LIGHT_RED,
+            underline: Stroke::new(1.0, Color32::LIGHT_RED),
+            ..normal.clone()
+        },
+    };
+
+    // the tokens before an invalid one are still highlighted
+    let tokens = query::tokenize(text)
+        .or_else(|e| query::tokenize(&text[..e.span.start]))
+        .unwrap_or_default();
+
+    let mut classes = vec![Class::Normal; text.len()];
+    for token in tokens {
+        let class = match &token.kind {
+            TokenKind::Ident(ident) if query::KEYWORDS.contains(&ident.as_str()) => Class::Keyword,
+            TokenKind::Ident(_) => Class::Field,
+            TokenKind::String(_) => Class::String,
+            TokenKind::Number(_) => Class::Number,
+            _ => Class::Normal,
+        };
+        classes[token.span].fill(class);
+    }
+    if let Err(e) = query::parse(text) {
+        classes[e.span].fill(Class::Error);
+    }
+
+    let mut job = LayoutJob::default();
+    job.wrap.max_width = wrap_width;
+
+    // the classes only change on token boundaries, which are char boundaries
+    let mut start = 0;
+    for end in 1..=text.len() {
+        if end == text.len() || classes[end] != classes[start] {
+            job.append(&text[start..end], 0.0, format(classes[start]));
+            start = end;
+        }
+    }
+
+    ui.fonts().layout_job(job)
+}
@@ -0,0 +1,51 @@
+use eframe::egui;
+use eframe::egui::{Color32, RichText};
+use serde::{Deserialize, Serialize};
+
+use crate::event::Event;
+use crate::query::{self, Query};
+use crate::query_editor::QueryEditor;
+use crate::subscriptions::Subscriptions;
+
+/// A query evaluated locally against the events a view received.
+///
+/// It splits a broad subscription between many views and filters the
+/// buffered events without reconnecting. An empty filter matches every event.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct QueryFilter {
+    source: String,
+    /// The parsed source, `None` when it is empty or invalid.
+    #[serde(skip)]
+    query: Option<Option<Query>>,
+}
+
+impl QueryFilter {
+    pub fn matches(&mut self, event: &Event) -> bool {
+        match self.query() {
+            Some(query) => query.matches(event),
+            None => true,
+        }
+    }
+
+    fn query(&mut self) -> Option<&Query> {
+        let source = &self.source;
+        self.query.get_or_insert_with(|| query::parse(source).ok()).as_ref()
+    }
+
+    /// Displays the filter editor, invalid filters are reported and match every event.
+    pub fn settings_ui(&mut self, ui: &mut egui::Ui, subscriptions: &Subscriptions) {
+        ui.label("local filter:").on_hover_text("Evaluated against the received events");
+        let response =
+            ui.add(QueryEditor::new("filter", &mut self.source, subscriptions.completions()));
+        if response.changed() {
+            self.query = None;
+        }
+
+        if !self.source.is_empty() {
+            if let Err(e) = query::parse(&self.source) {
+                ui.label(RichText::new(e.message).color(Color32::LIGHT_RED));
+            }
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use std::rc::Rc;
+
+use eframe::egui;
+use eframe::egui::{Color32, RichText};
+use instant::Instant;
+use serde::{Deserialize, Serialize};
+
+use crate::base_url;
+use crate::connection_status::ConnectionStatus;
+use crate::event::{self, ConnectionState, Event};
+use crate::query;
+use crate::query_builder::QueryBuilder;
+use crate::query_editor::QueryEditor;
+use crate::subscriptions::{Subscription, Subscriptions};
+
+/// The query of a view along with its subscription to the Riemann server.
+///
+/// It subscribes when the view is restored, when the query is edited and
+/// when the server URL changes. Only the query and its options are (de)serialized,
+/// the views flatten them next to their own settings.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuerySubscription {
+    query: String,
+    /// Loads the current state of the index before the live events.
+    load_index: bool,
+    #[serde(skip)]
+    subscription: Option<Subscription>,
+    /// The one-shot index query, dropped once the server sent every event.
+    #[serde(skip)]
+    snapshot: Option<Subscription>,
+    #[serde(skip)]
+    connect_error: Option<String>,
+    /// The failed connection attempts and the moment of the next one, after a connect error.
+    #[serde(skip)]
+    retry: Option<(u32, Instant)>,
+    /// The query was edited and must be subscribed to.
+    #[serde(skip)]
+    edited: bool,
+    /// The form of the query builder, derived from the query when opened.
+    #[serde(skip)]
+    builder: Option<QueryBuilder>,
+}
+
+/// The events received by a [`QuerySubscription`] since the last frame.
+pub struct Received {
+    /// The subscription changed, the previously received events must be forgotten.
+    pub resubscribed: bool,
+    pub events: Vec<Rc<Event>>,
+}
+
+impl QuerySubscription {
+    pub fn new(query: String) -> Self {
+        QuerySubscription { query, ..Default::default() }
+    }
+
+    /// Subscribes to the query if needed and returns the events received since the last call.
+    pub fn receive(&mut self, subscriptions: &mut Subscriptions) -> Received {
+        let server_changed = self
+            .subscription
+            .as_ref()
+            .is_some_and(|s| base_url(s.url().clone()) != base_url(subscriptions.url().clone()));
+        // the view was restored without its connection
+        let restored = self.subscription.is_none() && self.connect_error.is_none();
+        let retry = self.retry.is_some_and(|(_, at)| at <= Instant::now());
+
+        let mut resubscribed = false;
+        if (self.edited || server_changed || restored || retry) && query::parse(&self.query).is_ok()
+        {
+            let subscribed = subscriptions.subscribe(&self.query).and_then(|subscription| {
+                let snapshot = match self.load_index {
+                    true => Some(subscriptions.snapshot(&self.query)?),
+                    false => None,
+                };
+                Ok((subscription, snapshot))
+            });
+            match subscribed {
+                Ok((subscription, snapshot)) => {
+                    self.subscription = Some(subscription);
+                    self.snapshot = snapshot;
+                    self.connect_error = None;
+                    self.retry = None;
+                    resubscribed = true;
+                }
+                Err(e) => {
+                    // retries on the same backoff as the reconnections
+                    let attempt = match self.retry {
+                        Some((attempt, _)) if !self.edited && !server_changed => attempt + 1,
+                        _ => 0,
+                    };
+                    let backoff = event::backoff(attempt);
+                    self.retry = Some((attempt, Instant::now() + backoff));
+                    self.connect_error = Some(e);
+                    subscriptions.wakeup_after(backoff);
+                }
+            }
+        }
+        self.edited = false;
+
+        // the state of the index comes before the live events
+        let mut events = Vec::new();
+        if let Some(snapshot) = &self.snapshot {
+            events.extend(snapshot.take_events());
+            if matches!(snapshot.state(), ConnectionState::Closed { .. }) {
+                self.snapshot = None;
+            }
+        }
+        if let Some(subscription) = &self.subscription {
+            events.extend(subscription.take_events());
+        }
+        Received { resubscribed, events }
+    }
+
+    /// Displays the state of the connection.
+    pub fn status_ui(&self, ui: &mut egui::Ui) {
+        ui.add(
+            ConnectionStatus::new(self.subscription.as_ref())
+                .connect_error(self.connect_error.as_deref()),
+        );
+    }
+
+    /// Displays the query editor, the query is subscribed to when the editor loses focus
+    /// or when the query builder is applied. Invalid queries are reported and never subscribed to.
+    pub fn settings_ui(&mut self, ui: &mut egui::Ui, subscriptions: &Subscriptions) {
+        let load_index = ui
+            .checkbox(&mut self.load_index, "load the current index state")
+            .on_hover_text("Receive the events already indexed by the server before the new ones");
+        if load_index.changed() {
+            self.edited = true;
+        }
+
+        ui.label("query string:");
+        let response = ui.add(
+            QueryEditor::new("query", &mut self.query, subscriptions.completions()).multiline(true),
+        );
+        if response.changed() {
+            self.builder = None;
+        }
+
+        match query::parse(&self.query) {
+            Ok(_) => {
+                if response.lost_focus() {
+                    self.edited = true;
+                    ui.ctx().request_repaint();
+                }
+            }
+            Err(_) if self.query.is_empty() => (),
+            Err(e) => {
+                ui.label(RichText::new(e.message).color(Color32::LIGHT_RED));
+            }
+        }
+
+        ui.collapsing("query builder", |ui| {
+            if self.builder.is_none() {
+                self.builder = match query::parse(&self.query) {
+                    Ok(query) => QueryBuilder::from_query(&query),
+                    Err(_) if self.query.trim().is_empty() => Some(QueryBuilder::default()),
+                    Err(_) => None,
+                };
+            }
+
+            match &mut self.builder {
+                Some(builder) => {
+                    if builder.ui(ui) {
+                        self.query = builder.to_query_string();
+                        self.edited = true;
+                        ui.ctx().request_repaint();
+                    }
+                }
+                None => {
+                    ui.label("This query is too complex for the builder.");
+                    if ui.button("Start a new query").clicked() {
+                        self.builder = Some(QueryBuilder::default());
+                    }
+                }
+            }
+        });
+    }
+}
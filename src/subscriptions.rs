@@ -0,0 +1,232 @@
+use std::cell::RefCell;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+use std::sync::Arc;
+use std::time::Duration;
+
+use ewebsock::{WsEvent, WsMessage};
+use instant::Instant;
+use url::Url;
+
+use crate::event::{self, ConnectionState, Event, EventReceiver};
+use crate::query_editor::Completions;
+use crate::websocket_url;
+
+/// Deduplicates the websocket subscriptions of the views by server and query,
+/// every message is parsed once and the event is dispatched to every subscriber.
+pub struct Subscriptions {
+    url: Url,
+    wakeup: Arc<dyn Fn() + Send + Sync>,
+    connections: HashMap<Url, Connection>,
+    /// The one-shot index queries, never shared as they only send the events once.
+    snapshots: Vec<Connection>,
+    completions: Completions,
+    /// The moment the wakeup function is already scheduled to be called.
+    next_wakeup: Option<Instant>,
+}
+
+struct Connection {
+    event_receiver: EventReceiver,
+    subscribers: Vec<Weak<RefCell<Inbox>>>,
+}
+
+/// The maximum number of events waiting in an inbox, the oldest are dropped
+/// when the view isn't shown and doesn't take them.
+const MAX_INBOX_LEN: usize = 10_000;
+
+struct Inbox {
+    events: Vec<Rc<Event>>,
+    state: ConnectionState,
+    last_message: Option<Instant>,
+}
+
+impl Subscriptions {
+    pub fn new(url: Url) -> Subscriptions {
+        Subscriptions {
+            url,
+            wakeup: Arc::new(|| ()),
+            connections: HashMap::new(),
+            snapshots: Vec::new(),
+            completions: Completions::default(),
+            next_wakeup: None,
+        }
+    }
+
+    /// Sets the function called when a message arrives on a new connection,
+    /// usually used to request a repaint of the dashboard.
+    pub fn set_wakeup(&mut self, wakeup: impl Fn() + Send + Sync + 'static) {
+        self.wakeup = Arc::new(wakeup);
+    }
+
+    /// The URL of the Riemann server new subscriptions connect to.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    pub fn set_url(&mut self, url: Url) {
+        self.url = url;
+    }
+
+    /// Calls the wakeup function after this delay even if no message arrives,
+    /// used to refresh the views when their events expire.
+    pub fn wakeup_after(&mut self, delay: Duration) {
+        let at = Instant::now() + delay;
+        if self.next_wakeup.is_some_and(|next| Instant::now() < next && next <= at) {
+            return;
+        }
+        self.next_wakeup = Some(at);
+        event::wakeup_after(delay, self.wakeup.clone());
+    }
+
+    /// The values of the recently received events, suggested by the query editors.
+    pub fn completions(&self) -> &Completions {
+        &self.completions
+    }
+
+    /// Subscribes to the events matching this query, reusing the
+    /// connection of another subscriber to the same query if possible.
+    pub fn subscribe(&mut self, query: &str) -> ewebsock::Result<Subscription> {
+        let url = websocket_url(&self.url, true, query);
+        let connection = match self.connections.entry(url.clone()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let wakeup = self.wakeup.clone();
+                let wakeup = move || wakeup();
+                let event_receiver = EventReceiver::connect(url.clone(), wakeup)?;
+                entry.insert(Connection { event_receiver, subscribers: Vec::new() })
+            }
+        };
+
+        Ok(connection.subscribe(url))
+    }
+
+    /// Queries the current state of the index, the events matching
+    /// this query are received once and the connection is closed.
+    pub fn snapshot(&mut self, query: &str) -> ewebsock::Result<Subscription> {
+        let url = websocket_url(&self.url, false, query);
+        let wakeup = self.wakeup.clone();
+        let event_receiver = EventReceiver::connect_once(url.clone(), move || wakeup())?;
+        let mut connection = Connection { event_receiver, subscribers: Vec::new() };
+        let subscription = connection.subscribe(url);
+        self.snapshots.push(connection);
+        Ok(subscription)
+    }
+
+    /// Receives the pending messages of every connection and dispatches the
+    /// events to the subscribers, connections without subscribers are closed.
+    pub fn poll(&mut self) {
+        let completions = &mut self.completions;
+        self.connections.retain(|_url, connection| connection.dispatch(completions));
+        self.snapshots.retain_mut(|connection| {
+            connection.dispatch(completions)
+                && !matches!(connection.event_receiver.state(), ConnectionState::Closed { .. })
+        });
+    }
+}
+
+impl Connection {
+    fn subscribe(&mut self, url: Url) -> Subscription {
+        let inbox = Inbox {
+            events: Vec::new(),
+            state: self.event_receiver.state().clone(),
+            last_message: self.event_receiver.last_message(),
+        };
+        let inbox = Rc::new(RefCell::new(inbox));
+        self.subscribers.push(Rc::downgrade(&inbox));
+
+        Subscription { url, inbox }
+    }
+
+    /// Dispatches the pending events, returns `false` if there are no subscribers anymore.
+    fn dispatch(&mut self, completions: &mut Completions) -> bool {
+        self.subscribers.retain(|inbox| inbox.strong_count() > 0);
+        if self.subscribers.is_empty() {
+            return false;
+        }
+
+        let mut events = Vec::new();
+        while let Some(event) = self.event_receiver.try_recv() {
+            if let WsEvent::Message(WsMessage::Text(text)) = event {
+                if let Ok(event) = serde_json::from_str::<Event>(&text) {
+                    completions.observe(&event);
+                    events.push(Rc::new(event));
+                }
+            }
+        }
+
+        for inbox in self.subscribers.iter().filter_map(Weak::upgrade) {
+            let mut inbox = inbox.borrow_mut();
+            inbox.push(&events);
+            inbox.state = self.event_receiver.state().clone();
+            inbox.last_message = self.event_receiver.last_message();
+        }
+
+        true
+    }
+}
+
+impl Inbox {
+    fn push(&mut self, events: &[Rc<Event>]) {
+        self.events.extend(events.iter().cloned());
+        if self.events.len() > MAX_INBOX_LEN {
+            let diff = self.events.len() - MAX_INBOX_LEN;
+            self.events.drain(..diff);
+        }
+    }
+}
+
+impl Default for Subscriptions {
+    fn default() -> Self {
+        Subscriptions::new(Url::parse(crate::DEFAULT_URL).unwrap())
+    }
+}
+
+/// The events of a query, dropping it unsubscribes the view.
+pub struct Subscription {
+    url: Url,
+    inbox: Rc<RefCell<Inbox>>,
+}
+
+impl Subscription {
+    /// The websocket URL of the subscription, which contains the query.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Takes the events received since the last call.
+    pub fn take_events(&self) -> Vec<Rc<Event>> {
+        std::mem::take(&mut self.inbox.borrow_mut().events)
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        self.inbox.borrow().state.clone()
+    }
+
+    /// The moment the last message was received on the connection.
+    pub fn last_message(&self) -> Option<Instant> {
+        self.inbox.borrow().last_message
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inboxes_keep_the_newest_events() {
+        let mut inbox =
+            Inbox { events: Vec::new(), state: ConnectionState::Open, last_message: None };
+        let events: Vec<_> = (0..MAX_INBOX_LEN + 10)
+            .map(|i| {
+                let json = serde_json::json!({ "time": null, "metric": i, "tags": [] });
+                Rc::new(serde_json::from_value(json).unwrap())
+            })
+            .collect();
+        inbox.push(&events[..10]);
+        inbox.push(&events[10..]);
+
+        assert_eq!(inbox.events.len(), MAX_INBOX_LEN);
+        assert_eq!(inbox.events[0].metric, Some(10.0));
+    }
+}
@@ -0,0 +1,55 @@
+use std::io::Write;
+
+use ewebsock::{WsEvent, WsMessage};
+use riemann_egui_dash::{websocket_url, Event, EventReceiver};
+use time::format_description::well_known::Rfc3339;
+use url::Url;
+
+/// Subscribes to the query and prints the matching events on the standard output,
+/// reconnects to the server when the connection is lost.
+pub async fn tail(url: &Url, query: &str, ndjson: bool) -> anyhow::Result<()> {
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let wakeup = move || {
+        let _ = tx.send(());
+    };
+    let url = websocket_url(url, true, query);
+    let mut event_receiver = EventReceiver::connect(url, wakeup).map_err(anyhow::Error::msg)?;
+
+    let stdout = std::io::stdout();
+    while rx.recv().await.is_some() {
+        let mut stdout = stdout.lock();
+        while let Some(event) = event_receiver.try_recv() {
+            match event {
+                WsEvent::Opened => tracing::info!("connected to {}", event_receiver.url()),
+                WsEvent::Message(WsMessage::Text(text)) => match serde_json::from_str(&text) {
+                    // the server already sends one JSON event per message
+                    Ok(_) if ndjson => writeln!(stdout, "{}", text.trim_end())?,
+                    Ok(event) => writeln!(stdout, "{}", format_event(&event))?,
+                    Err(e) => tracing::warn!("invalid event received: {}", e),
+                },
+                WsEvent::Message(_) => (),
+                WsEvent::Error(e) => tracing::warn!("{}, reconnecting", e),
+                WsEvent::Closed => tracing::warn!("connection closed, reconnecting"),
+            }
+        }
+        stdout.flush()?;
+    }
+
+    Ok(())
+}
+
+fn format_event(event: &Event) -> String {
+    let Event { time, host, service, state, metric, description, .. } = event;
+    let time = time.and_then(|t| t.format(&Rfc3339).ok());
+    let metric = metric.map(|m| format!("{:.02?}", m));
+    let fields = [
+        time.as_deref(),
+        host.as_deref(),
+        service.as_deref(),
+        state.as_deref(),
+        metric.as_deref(),
+        description.as_deref(),
+    ];
+    let fields: Vec<_> = fields.iter().map(|field| field.unwrap_or("-")).collect();
+    fields.join("\t")
+}
@@ -1,11 +1,12 @@
 use eframe::egui;
-use url::Url;
+use serde::{Deserialize, Serialize};
 
 use super::View;
+use crate::subscriptions::Subscriptions;
 
 pub const DEFAULT_TITLE: &str = "About this";
 
-#[derive(Default)]
+#[derive(Default, Serialize, Deserialize)]
 pub struct About;
 
 impl View for About {
@@ -13,15 +14,19 @@ impl View for About {
         DEFAULT_TITLE.to_string()
     }
 
-    fn show(&mut self, ctx: &egui::Context, id: egui::Id, url: &Url, open: &mut bool) {
-        egui::Window::new(self.title())
-            .id(id)
+    fn show(
+        &mut self,
+        ctx: &egui::Context,
+        window: egui::Window<'_>,
+        subscriptions: &mut Subscriptions,
+    ) -> Option<egui::Rect> {
+        window
             .default_width(380.0)
-            .open(open)
-            .show(ctx, |ui| self.ui(ui, url));
+            .show(ctx, |ui| self.ui(ui, subscriptions))
+            .map(|inner| inner.response.rect)
     }
 
-    fn ui(&mut self, ui: &mut egui::Ui, _url: &Url) {
+    fn ui(&mut self, ui: &mut egui::Ui, _subscriptions: &mut Subscriptions) {
         use egui::special_emojis::{OS_APPLE, OS_LINUX, OS_WINDOWS};
 
         ui.style_mut().spacing.interact_size.y = 0.0; // hack to make `horizontal_wrapped` work better with text.
@@ -1,20 +1,210 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::rc::Rc;
+
 use eframe::egui;
-use eframe::egui::TextStyle;
-use ewebsock::{WsEvent, WsMessage};
-use url::Url;
+use eframe::egui::{vec2, Pos2, RichText, Sense, Shape, Stroke};
+use serde::{Deserialize, Serialize};
 
-use super::View;
-use crate::event::{Event, EventReceiver};
-use crate::{base_url, websocket_url};
+use super::{insert_latest, state_color, state_severity, Expiry, View};
+use crate::event::{self, Event};
+use crate::query_filter::QueryFilter;
+use crate::query_subscription::QuerySubscription;
+use crate::subscriptions::Subscriptions;
 
 pub const DEFAULT_TITLE: &str = "🔢 Big Number";
 
+/// The number of metrics displayed by the sparkline.
+const HISTORY_LEN: usize = 100;
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct BigNumber {
-    query: String,
+    #[serde(flatten)]
+    query: QuerySubscription,
+    filter: QueryFilter,
     title: String,
-    current_metric: Option<f32>,
-    current_state_ok: Option<bool>,
-    event_receiver: Option<EventReceiver>,
+    format: NumberFormat,
+    decimals: usize,
+    /// Appended to the formatted number, after the prefix of the SI and bytes formats.
+    unit: String,
+    sparkline: bool,
+    aggregation: Aggregation,
+    expiry: Expiry,
+    /// The latest event of every host and service.
+    #[serde(skip)]
+    latest: BTreeMap<(String, String), Rc<Event>>,
+    #[serde(skip)]
+    history: VecDeque<f64>,
+}
+
+/// How the latest events of the hosts and services are reduced to a single number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum Aggregation {
+    /// The metric of the most recent event.
+    Latest,
+    Sum,
+    Mean,
+    Min,
+    Max,
+    /// The number of events, with or without a metric.
+    Count,
+}
+
+impl Aggregation {
+    const ALL: &'static [Aggregation] = &[
+        Aggregation::Latest,
+        Aggregation::Sum,
+        Aggregation::Mean,
+        Aggregation::Min,
+        Aggregation::Max,
+        Aggregation::Count,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Aggregation::Latest => "latest",
+            Aggregation::Sum => "sum",
+            Aggregation::Mean => "mean",
+            Aggregation::Min => "min",
+            Aggregation::Max => "max",
+            Aggregation::Count => "count",
+        }
+    }
+
+    fn aggregate<'a>(&self, events: impl Iterator<Item = &'a Event> + Clone) -> Option<f64> {
+        let metrics = events.clone().filter_map(|event| event.metric.map(f64::from));
+        match self {
+            Aggregation::Latest => latest(events).and_then(|event| event.metric).map(f64::from),
+            Aggregation::Sum => metrics.reduce(|a, b| a + b),
+            Aggregation::Mean => {
+                let (sum, count) = metrics.fold((0.0, 0), |(sum, count), m| (sum + m, count + 1));
+                (count > 0).then(|| sum / count as f64)
+            }
+            Aggregation::Min => metrics.reduce(f64::min),
+            Aggregation::Max => metrics.reduce(f64::max),
+            Aggregation::Count => Some(events.count() as f64),
+        }
+    }
+}
+
+/// The most recent event, by time.
+fn latest<'a>(events: impl Iterator<Item = &'a Event>) -> Option<&'a Event> {
+    events.max_by_key(|event| event.time)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum NumberFormat {
+    Fixed,
+    /// With a k, M, G... prefix.
+    Si,
+    /// With a Ki, Mi, Gi... prefix and a B unit.
+    Bytes,
+    /// A ratio between 0 and 1.
+    Percent,
+}
+
+impl NumberFormat {
+    const ALL: &'static [NumberFormat] =
+        &[NumberFormat::Fixed, NumberFormat::Si, NumberFormat::Bytes, NumberFormat::Percent];
+
+    fn label(&self) -> &'static str {
+        match self {
+            NumberFormat::Fixed => "fixed",
+            NumberFormat::Si => "SI",
+            NumberFormat::Bytes => "bytes",
+            NumberFormat::Percent => "percent",
+        }
+    }
+
+    fn format(&self, value: f64, decimals: usize, unit: &str) -> String {
+        match self {
+            NumberFormat::Fixed => {
+                format!("{:.*} {}", decimals, value, unit).trim_end().to_string()
+            }
+            NumberFormat::Si => {
+                const PREFIXES: &[&str] = &["n", "µ", "m", "", "k", "M", "G", "T", "P", "E"];
+                let exponent = match value {
+                    v if v == 0.0 || !v.is_finite() => 0,
+                    v => (v.abs().log10() / 3.0).floor() as i32,
+                };
+                let exponent = exponent.clamp(-3, PREFIXES.len() as i32 - 4);
+                let scaled = value / 1000f64.powi(exponent);
+                let prefix = PREFIXES[(exponent + 3) as usize];
+                format!("{:.*} {}{}", decimals, scaled, prefix, unit).trim_end().to_string()
+            }
+            NumberFormat::Bytes => {
+                const PREFIXES: &[&str] = &["", "Ki", "Mi", "Gi", "Ti", "Pi", "Ei"];
+                let mut scaled = value;
+                let mut prefix = 0;
+                while scaled.abs() >= 1024.0 && prefix < PREFIXES.len() - 1 {
+                    scaled /= 1024.0;
+                    prefix += 1;
+                }
+                format!("{:.*} {}B{}", decimals, scaled, PREFIXES[prefix], unit)
+            }
+            NumberFormat::Percent => format!("{:.*}%{}", decimals, value * 100.0, unit),
+        }
+    }
+}
+
+impl BigNumber {
+    pub fn new(title: String, query: String) -> Self {
+        Self { title, query: QuerySubscription::new(query), ..Default::default() }
+    }
+
+    /// The events the number is computed from and whether they all expired. Under
+    /// [`Expiry::Grey`] the expired events are left out, unless none is up to date.
+    fn inputs(&self, now: f64) -> (Vec<&Event>, bool) {
+        let events: Vec<_> = self.latest.values().map(Rc::as_ref).collect();
+        if self.expiry != Expiry::Grey {
+            return (events, false);
+        }
+
+        let fresh: Vec<_> = events.iter().copied().filter(|e| !e.is_expired(now)).collect();
+        match fresh.is_empty() && !events.is_empty() {
+            true => (events, true),
+            false => (fresh, false),
+        }
+    }
+
+    fn aggregate(&self, events: &[&Event]) -> Option<f64> {
+        self.aggregation.aggregate(events.iter().copied())
+    }
+
+    /// The state displayed along with the number: the one of the most
+    /// recent event or the worst one when the events are aggregated.
+    fn state<'a>(&self, events: &[&'a Event]) -> Option<&'a str> {
+        let events = events.iter().copied();
+        let event = match self.aggregation {
+            Aggregation::Latest => latest(events),
+            _ => events.max_by_key(|event| state_severity(event.state.as_deref())),
+        };
+        event.and_then(|event| event.state.as_deref())
+    }
+
+    fn sparkline_ui(&self, ui: &mut egui::Ui, color: egui::Color32) {
+        let width = ui.available_width().max(60.0);
+        let (rect, _) = ui.allocate_exact_size(vec2(width, 30.0), Sense::hover());
+        if self.history.len() < 2 {
+            return;
+        }
+
+        let min = self.history.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = self.history.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let range = if max > min { max - min } else { 1.0 };
+        let step = rect.width() / (HISTORY_LEN - 1) as f32;
+        let left = rect.right() - step * (self.history.len() - 1) as f32;
+        let points = self
+            .history
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                let y = rect.bottom() - ((value - min) / range) as f32 * rect.height();
+                Pos2::new(left + step * i as f32, y)
+            })
+            .collect();
+        ui.painter_at(rect).add(Shape::line(points, Stroke::new(1.5, color)));
+    }
 }
 
 impl View for BigNumber {
@@ -22,62 +212,109 @@ impl View for BigNumber {
         DEFAULT_TITLE.to_string()
     }
 
-    fn show(&mut self, ctx: &egui::Context, id: egui::Id, url: &Url, open: &mut bool) {
-        egui::Window::new(self.title()).id(id).open(open).show(ctx, |ui| self.ui(ui, url));
+    fn show(
+        &mut self,
+        ctx: &egui::Context,
+        window: egui::Window<'_>,
+        subscriptions: &mut Subscriptions,
+    ) -> Option<egui::Rect> {
+        window.show(ctx, |ui| self.ui(ui, subscriptions)).map(|inner| inner.response.rect)
     }
 
-    fn ui(&mut self, ui: &mut egui::Ui, url: &Url) {
-        if let Some(event_receiver) = &self.event_receiver {
-            while let Some(event) = event_receiver.try_recv() {
-                if let WsEvent::Message(WsMessage::Text(text)) = event {
-                    if let Ok(Event { metric, state, .. }) = serde_json::from_str::<Event>(&text) {
-                        self.current_metric = metric;
-                        self.current_state_ok = state.map(|s| s == "ok");
-                    }
-                }
+    fn ui(&mut self, ui: &mut egui::Ui, subscriptions: &mut Subscriptions) {
+        let received = self.query.receive(subscriptions);
+        if received.resubscribed {
+            self.latest.clear();
+            self.history.clear();
+        }
+        let mut updated = false;
+        for event in received.events.into_iter().filter(|event| self.filter.matches(event)) {
+            let host = event.host.clone().unwrap_or_default();
+            let service = event.service.clone().unwrap_or_default();
+            updated |= insert_latest(&mut self.latest, (host, service), event);
+        }
+
+        let now = event::unix_now();
+        if self.expiry == Expiry::Remove {
+            self.latest.retain(|_, event| !event.is_expired(now));
+        }
+        self.expiry.schedule(subscriptions, now, self.latest.values().map(Rc::as_ref));
+
+        let value = self.aggregate(&self.inputs(now).0);
+        if let Some(value) = value.filter(|_| updated) {
+            if self.history.len() == HISTORY_LEN {
+                self.history.pop_front();
             }
+            self.history.push_back(value);
         }
 
-        // TODO(kerollmops) create a simple function for that
+        self.query.status_ui(ui);
+
         ui.collapsing("Settings", |ui| {
             ui.group(|ui| {
                 egui::ScrollArea::vertical().show(ui, |ui| {
                     ui.label("title:");
                     ui.add(egui::TextEdit::singleline(&mut self.title));
 
-                    ui.label("query string:");
-                    let response = ui.add(
-                        egui::TextEdit::multiline(&mut self.query)
-                            .font(TextStyle::Monospace) // for cursor height
-                            .code_editor()
-                            .desired_rows(3)
-                            .lock_focus(true)
-                            .desired_width(f32::INFINITY),
-                    );
-
-                    if (response.lost_focus() && !self.query.is_empty())
-                        || self
-                            .event_receiver
-                            .as_ref()
-                            .map_or(false, |er| base_url(er.url().clone()) != base_url(url.clone()))
-                    {
-                        let wakeup = move || ();
-                        let url = websocket_url(url, true, &self.query);
-                        if let Ok(event_receiver) = EventReceiver::connect(url, wakeup) {
-                            self.current_metric = None;
-                            self.event_receiver = Some(event_receiver);
-                        }
-                    }
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_id_source("format")
+                            .selected_text(self.format.label())
+                            .show_ui(ui, |ui| {
+                                for format in NumberFormat::ALL {
+                                    ui.selectable_value(&mut self.format, *format, format.label());
+                                }
+                            });
+                        ui.add(egui::DragValue::new(&mut self.decimals).clamp_range(0..=6))
+                            .on_hover_text("decimals");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("unit:");
+                        ui.add(egui::TextEdit::singleline(&mut self.unit).desired_width(60.0));
+                    });
+                    egui::ComboBox::from_label("aggregation")
+                        .selected_text(self.aggregation.label())
+                        .show_ui(ui, |ui| {
+                            for aggregation in Aggregation::ALL {
+                                let label = aggregation.label();
+                                ui.selectable_value(&mut self.aggregation, *aggregation, label);
+                            }
+                        })
+                        .response
+                        .on_hover_text("Computed over the latest event of every host and service");
+                    ui.checkbox(&mut self.sparkline, "sparkline");
+                    self.expiry.ui(ui);
+
+                    self.query.settings_ui(ui, subscriptions);
+                    self.filter.settings_ui(ui, subscriptions);
                 });
             });
         });
 
         ui.heading(&self.title);
 
-        match self.current_metric {
-            Some(num) => ui.heading(format!("{:.02?}", num)),
-            None => ui.heading("-.--"),
+        // greyed when none of the aggregated events is up to date
+        let (events, expired) = self.inputs(now);
+        let text = match self.aggregate(&events) {
+            Some(num) => RichText::new(self.format.format(num, self.decimals, &self.unit)),
+            None => RichText::new("-.--"),
+        };
+        let state = self.state(&events);
+        let color = match state.and_then(state_color) {
+            _ if expired => ui.visuals().weak_text_color(),
+            Some(color) => color,
+            None => ui.visuals().text_color(),
         };
+        let response = ui.heading(text.color(color));
+        if let Some(state) = state {
+            response.on_hover_text(match expired {
+                true => format!("{} (expired)", state),
+                false => state.to_string(),
+            });
+        }
+
+        if self.sparkline {
+            self.sparkline_ui(ui, color);
+        }
     }
 }
 
@@ -85,10 +322,57 @@ impl Default for BigNumber {
     fn default() -> Self {
         Self {
             query: Default::default(),
-            title: Default::default(),
-            current_metric: Default::default(),
-            current_state_ok: Default::default(),
-            event_receiver: Default::default(),
+            filter: Default::default(),
+            title: String::new(),
+            format: NumberFormat::Fixed,
+            decimals: 2,
+            unit: String::new(),
+            sparkline: true,
+            aggregation: Aggregation::Latest,
+            expiry: Expiry::default(),
+            latest: BTreeMap::new(),
+            history: VecDeque::new(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An event of this host sent at 00:00:00 and valid for `ttl` seconds.
+    fn event(host: &str, metric: f32, ttl: f32, state: &str) -> Rc<Event> {
+        let json = serde_json::json!({
+            "host": host,
+            "time": "2026-01-01T00:00:00Z",
+            "metric": metric,
+            "ttl": ttl,
+            "state": state,
+            "tags": [],
+        });
+        Rc::new(serde_json::from_value(json).unwrap())
+    }
+
+    #[test]
+    fn expired_events_are_left_out_of_the_aggregate() {
+        let mut big_number = BigNumber { aggregation: Aggregation::Sum, ..Default::default() };
+        for event in [event("a", 1.0, 10.0, "critical"), event("b", 2.0, 60.0, "ok")] {
+            let key = (event.host.clone().unwrap(), String::new());
+            big_number.latest.insert(key, event);
+        }
+        let midnight = 1_767_225_600.0;
+
+        let (events, expired) = big_number.inputs(midnight + 30.0);
+        assert_eq!((big_number.aggregate(&events), expired), (Some(2.0), false));
+        assert_eq!(big_number.state(&events), Some("ok"));
+
+        // greyed but still displayed once every event expired
+        let (events, expired) = big_number.inputs(midnight + 90.0);
+        assert_eq!((big_number.aggregate(&events), expired), (Some(3.0), true));
+        assert_eq!(big_number.state(&events), Some("critical"));
+
+        big_number.expiry = Expiry::Ignore;
+        let (events, expired) = big_number.inputs(midnight + 30.0);
+        assert_eq!((big_number.aggregate(&events), expired), (Some(3.0), false));
+    }
+}
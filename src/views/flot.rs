@@ -1,113 +1,716 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::mem::take;
+use std::rc::Rc;
+use std::time::Duration;
 
 use eframe::egui;
-use eframe::egui::plot::{Legend, Line, Plot, Value, Values};
-use eframe::egui::TextStyle;
-use ewebsock::{WsEvent, WsMessage};
-use url::Url;
+use eframe::egui::plot::{
+    Bar, BarChart, HLine, Legend, Line, LineStyle, Plot, PlotUi, Points, Polygon, Value, Values,
+};
+use eframe::egui::Color32;
+use serde::{Deserialize, Serialize};
+use time::{OffsetDateTime, UtcOffset};
 
-use super::View;
-use crate::event::{Event, EventReceiver};
-use crate::{base_url, websocket_url};
+use super::{state_color, threshold_ui, View};
+use crate::event::{self, Event};
+use crate::query_filter::QueryFilter;
+use crate::query_subscription::QuerySubscription;
+use crate::subscriptions::Subscriptions;
 
 pub const DEFAULT_TITLE: &str = "📈 Flot Graph";
 
+/// The maximum number of events kept by a series, whatever the retention.
+const MAX_SERIES_LEN: usize = 10_000;
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct Flot {
-    query: String,
+    #[serde(flatten)]
+    query: QuerySubscription,
+    filter: QueryFilter,
+    title: String,
+    retention: Retention,
+    /// The number of events kept by the graph.
     limit: f32,
-    events: Vec<Event>,
-    event_receiver: Option<EventReceiver>,
+    /// The duration covered by every series, in seconds.
+    window: f32,
+    group_by: GroupBy,
+    /// The legend of the series, like `{host} {service}`, the series key when empty.
+    label: String,
+    transform: Transform,
+    /// The duration covered by the rolling transforms, in seconds.
+    rolling_window: f32,
+    /// The percentile computed by [`Transform::RollingPercentile`], from 0 to 100.
+    percentile: f32,
+    /// Plots the sum of the transformed series as a single series.
+    sum: bool,
+    render: Render,
+    time_zone: TimeZone,
+    /// Keeps the current time visible, zooming and dragging are disabled.
+    follow: bool,
+    warning: Option<f32>,
+    critical: Option<f32>,
+    /// Colors the background while the events of a series are not ok.
+    shade_states: bool,
+    /// The events of every series, oldest first.
+    #[serde(skip)]
+    series: BTreeMap<String, VecDeque<Rc<Event>>>,
+    /// The grouping of the current series.
+    #[serde(skip)]
+    grouped_by: Option<GroupBy>,
 }
 
-impl View for Flot {
-    fn title(&self) -> String {
-        DEFAULT_TITLE.to_string()
+/// The field splitting the events in series.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+enum GroupBy {
+    Host,
+    Service,
+    HostService,
+    /// A series by tag, an event with many tags is in many series.
+    Tag,
+    /// A series by value of this custom attribute.
+    Attribute(String),
+}
+
+impl GroupBy {
+    fn label(&self) -> &'static str {
+        match self {
+            GroupBy::Host => "host",
+            GroupBy::Service => "service",
+            GroupBy::HostService => "host and service",
+            GroupBy::Tag => "tag",
+            GroupBy::Attribute(_) => "attribute",
+        }
+    }
+
+    /// The keys of the series the event belongs to.
+    fn keys(&self, event: &Event) -> Vec<String> {
+        match self {
+            GroupBy::Host => event.host.iter().cloned().collect(),
+            GroupBy::Service => event.service.iter().cloned().collect(),
+            GroupBy::HostService => match (&event.host, &event.service) {
+                (Some(host), Some(service)) => vec![format!("{} {}", host, service)],
+                _ => Vec::new(),
+            },
+            GroupBy::Tag => event.tags.clone(),
+            GroupBy::Attribute(name) => attribute(event, name).into_iter().collect(),
+        }
+    }
+}
+
+fn attribute(event: &Event, name: &str) -> Option<String> {
+    match event.attributes.get(name)? {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Null => None,
+        value => Some(value.to_string()),
+    }
+}
+
+/// Replaces the `{host}`, `{service}`, `{state}`, `{key}` and `{attribute}`
+/// placeholders of the template with the fields of the event.
+fn format_label(template: &str, key: &str, event: &Event) -> String {
+    if template.is_empty() {
+        return key.to_string();
+    }
+
+    let mut label = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+        label.push_str(&rest[..start]);
+        let value = match &rest[start + 1..end] {
+            "host" => event.host.clone(),
+            "service" => event.service.clone(),
+            "state" => event.state.clone(),
+            "key" => Some(key.to_string()),
+            name => attribute(event, name),
+        };
+        label.push_str(&value.unwrap_or_default());
+        rest = &rest[end + 1..];
+    }
+    label.push_str(rest);
+    label
+}
+
+/// How the metrics of a series are transformed before being plotted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum Transform {
+    Raw,
+    /// The increase per second of a counter, a decrease is considered as a reset.
+    Rate,
+    /// The change per second, negative when the metric decreases.
+    Derivative,
+    MovingAverage,
+    RollingMin,
+    RollingMax,
+    RollingPercentile,
+}
+
+impl Transform {
+    const ALL: &'static [Transform] = &[
+        Transform::Raw,
+        Transform::Rate,
+        Transform::Derivative,
+        Transform::MovingAverage,
+        Transform::RollingMin,
+        Transform::RollingMax,
+        Transform::RollingPercentile,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Transform::Raw => "raw",
+            Transform::Rate => "rate /s",
+            Transform::Derivative => "derivative",
+            Transform::MovingAverage => "moving average",
+            Transform::RollingMin => "rolling min",
+            Transform::RollingMax => "rolling max",
+            Transform::RollingPercentile => "rolling percentile",
+        }
+    }
+
+    fn is_rolling(&self) -> bool {
+        matches!(
+            self,
+            Transform::MovingAverage
+                | Transform::RollingMin
+                | Transform::RollingMax
+                | Transform::RollingPercentile
+        )
+    }
+
+    /// Transforms the points of a series sorted by time, `window` is in millis.
+    fn apply(&self, points: &[Value], window: f64, percentile: f64) -> Vec<Value> {
+        // the settings may come from an edited document
+        let percentile = percentile.clamp(0.0, 100.0);
+        match self {
+            Transform::Raw => points.to_vec(),
+            Transform::Rate | Transform::Derivative => points
+                .windows(2)
+                .filter(|pair| pair[1].x > pair[0].x)
+                .map(|pair| {
+                    let (previous, current) = (pair[0], pair[1]);
+                    let delta = match current.y - previous.y {
+                        // the counter has been reset, it increased by its current value
+                        delta if delta < 0.0 && *self == Transform::Rate => current.y,
+                        delta => delta,
+                    };
+                    let seconds = (current.x - previous.x) / 1000.0;
+                    Value { x: current.x, y: delta / seconds }
+                })
+                .collect(),
+            rolling => {
+                let mut start = 0;
+                let mut values = Vec::new();
+                points
+                    .iter()
+                    .enumerate()
+                    .map(|(end, point)| {
+                        // a window always contains the current point
+                        while start < end && points[start].x <= point.x - window {
+                            start += 1;
+                        }
+                        let window = points[start..=end].iter().map(|p| p.y);
+                        let y = match rolling {
+                            Transform::RollingMin => window.fold(f64::INFINITY, f64::min),
+                            Transform::RollingMax => window.fold(f64::NEG_INFINITY, f64::max),
+                            Transform::RollingPercentile => {
+                                values.clear();
+                                values.extend(window);
+                                values.sort_by(f64::total_cmp);
+                                let rank = percentile / 100.0 * (values.len() - 1) as f64;
+                                values[rank.round() as usize]
+                            }
+                            _ => window.sum::<f64>() / (end - start + 1) as f64,
+                        };
+                        Value { x: point.x, y }
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Sums the series, every series keeps its last value until its next point.
+fn sum(series: &[Vec<Value>]) -> Vec<Value> {
+    stack(series).pop().unwrap_or_default()
+}
+
+/// Stacks the series on top of each other, a series is the sum of itself and the
+/// previous ones. Every series keeps its last value until its next point.
+fn stack(series: &[Vec<Value>]) -> Vec<Vec<Value>> {
+    let mut points: Vec<_> = series
+        .iter()
+        .enumerate()
+        .flat_map(|(i, points)| points.iter().map(move |point| (i, *point)))
+        .collect();
+    points.sort_by(|(_, a), (_, b)| a.x.total_cmp(&b.x));
+
+    let mut current = vec![0.0; series.len()];
+    let mut stacked: Vec<Vec<Value>> = vec![Vec::new(); series.len()];
+    for (i, point) in points {
+        current[i] = point.y;
+        let mut y = 0.0;
+        for (layer, value) in stacked.iter_mut().zip(&current) {
+            y += value;
+            match layer.last_mut() {
+                // the points at the same time are merged
+                Some(last) if last.x == point.x => last.y = y,
+                _ => layer.push(Value { x: point.x, y }),
+            }
+        }
+    }
+    stacked
+}
+
+/// How the series are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum Render {
+    Line,
+    /// A line keeping its value until the next point.
+    Step,
+    Points,
+    Bars,
+    /// Filled lines, every series on top of the previous ones.
+    StackedArea,
+}
+
+impl Render {
+    const ALL: &'static [Render] =
+        &[Render::Line, Render::Step, Render::Points, Render::Bars, Render::StackedArea];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Render::Line => "line",
+            Render::Step => "step",
+            Render::Points => "points",
+            Render::Bars => "bars",
+            Render::StackedArea => "stacked area",
+        }
+    }
+
+    fn draw(&self, plot_ui: &mut PlotUi, label: String, points: Vec<Value>) {
+        match self {
+            Render::Line => plot_ui.line(Line::new(Values::from_values(points)).name(label)),
+            Render::Step => {
+                let mut steps = Vec::with_capacity(points.len() * 2);
+                for pair in points.windows(2) {
+                    steps.push(pair[0]);
+                    steps.push(Value { x: pair[1].x, y: pair[0].y });
+                }
+                steps.extend(points.last());
+                plot_ui.line(Line::new(Values::from_values(steps)).name(label));
+            }
+            Render::Points => {
+                plot_ui.points(Points::new(Values::from_values(points)).radius(2.0).name(label))
+            }
+            Render::Bars => {
+                // the bars of the closest points must not overlap
+                let width = points
+                    .windows(2)
+                    .map(|pair| pair[1].x - pair[0].x)
+                    .filter(|dx| *dx > 0.0)
+                    .min_by(f64::total_cmp)
+                    .unwrap_or(1000.0);
+                let bars = points.iter().map(|p| Bar::new(p.x, p.y).width(width * 0.8)).collect();
+                plot_ui.bar_chart(BarChart::new(bars).name(label));
+            }
+            Render::StackedArea => {
+                plot_ui.line(Line::new(Values::from_values(points)).fill(0.0).name(label))
+            }
+        }
+    }
+}
+
+/// The time ranges, in millis, during which the events were not ok along
+/// with the color of their state. An event lasts until the next one.
+fn state_regions<'a>(
+    events: impl Iterator<Item = &'a Rc<Event>>,
+    now: f64,
+) -> Vec<(f64, f64, Color32)> {
+    let mut states: Vec<_> = events
+        .filter_map(|event| {
+            let time = (event.time?.unix_timestamp_nanos() / 1_000_000) as f64;
+            let color = match event.state.as_deref()? {
+                "ok" => None,
+                state => Some(state_color(state).unwrap_or(Color32::GRAY)),
+            };
+            Some((time, color))
+        })
+        .collect();
+    // the last event lasts until now
+    if let Some(&(time, _)) = states.last() {
+        states.push((now.max(time), None));
+    }
+
+    let mut regions: Vec<(f64, f64, Color32)> = Vec::new();
+    for pair in states.windows(2) {
+        let ((start, color), (end, _)) = (pair[0], pair[1]);
+        let color = match color {
+            Some(color) => color,
+            None => continue,
+        };
+        match regions.last_mut() {
+            // the consecutive events with the same state are merged
+            Some(last) if last.1 == start && last.2 == color => last.1 = end,
+            _ => regions.push((start, end, color)),
+        }
+    }
+    regions
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum TimeZone {
+    Local,
+    Utc,
+}
+
+impl TimeZone {
+    fn offset(&self) -> UtcOffset {
+        match self {
+            TimeZone::Local => event::local_offset(),
+            TimeZone::Utc => UtcOffset::UTC,
+        }
+    }
+}
+
+/// Formats a time in millis since the Unix epoch, the precision depends
+/// on the visible time range, in millis too.
+fn format_time(millis: f64, offset: UtcOffset, range: f64) -> String {
+    let nanos = millis as i128 * 1_000_000;
+    let time = match OffsetDateTime::from_unix_timestamp_nanos(nanos) {
+        Ok(time) => time.to_offset(offset),
+        Err(_) => return String::new(),
+    };
+    let (hour, minute, second) = time.to_hms();
+    if range < 120_000.0 {
+        format!("{:02}:{:02}:{:02}", hour, minute, second)
+    } else if range < 86_400_000.0 {
+        format!("{:02}:{:02}", hour, minute)
+    } else {
+        format!("{:02}-{:02} {:02}:{:02}", time.month() as u8, time.day(), hour, minute)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum Retention {
+    /// Keeps the last `limit` events of the graph.
+    Messages,
+    /// Keeps the events of the last `window` seconds, every series covers the same time range.
+    Window,
+}
+
+impl Flot {
+    pub fn new(title: String, query: String) -> Self {
+        Self { title, query: QuerySubscription::new(query), ..Default::default() }
+    }
+
+    /// Inserts the event in its series sorted by time, the events
+    /// of the index may arrive after newer live ones.
+    fn insert(&mut self, event: Rc<Event>) {
+        for key in self.group_by.keys(&event) {
+            let events = self.series.entry(key).or_default();
+            let position = events.iter().rposition(|e| e.time <= event.time).map_or(0, |i| i + 1);
+            events.insert(position, event.clone());
+        }
     }
 
-    fn show(&mut self, ctx: &egui::Context, id: egui::Id, url: &Url, open: &mut bool) {
-        egui::Window::new(self.title()).id(id).open(open).show(ctx, |ui| self.ui(ui, url));
+    /// Splits the events again when the grouping changed.
+    fn regroup(&mut self) {
+        if self.grouped_by.as_ref() == Some(&self.group_by) {
+            return;
+        }
+        self.grouped_by = Some(self.group_by.clone());
+
+        let mut events: Vec<_> = std::mem::take(&mut self.series).into_values().flatten().collect();
+        // an event in many series must only be inserted once
+        events.sort_by_key(Rc::as_ptr);
+        events.dedup_by(|a, b| Rc::ptr_eq(a, b));
+        events.sort_by_key(|event| event.time);
+        for event in events {
+            self.insert(event);
+        }
     }
 
-    fn ui(&mut self, ui: &mut egui::Ui, url: &Url) {
-        if let Some(event_receiver) = &self.event_receiver {
-            while let Some(event) = event_receiver.try_recv() {
-                if let WsEvent::Message(WsMessage::Text(text)) = event {
-                    if let Ok(event) = serde_json::from_str::<Event>(&text) {
-                        self.events.push(event);
+    /// Removes the events that are out of the retention of the graph.
+    fn retain(&mut self, now: f64) {
+        match self.retention {
+            Retention::Messages => self.retain_messages((self.limit as usize).min(MAX_SERIES_LEN)),
+            Retention::Window => {
+                let oldest = now - f64::from(self.window);
+                for events in self.series.values_mut() {
+                    let expired =
+                        events.iter().take_while(|event| !is_after(event, oldest)).count();
+                    events.drain(..expired);
+                    if events.len() > MAX_SERIES_LEN {
+                        let diff = events.len() - MAX_SERIES_LEN;
+                        events.drain(..diff);
                     }
                 }
             }
         }
+        self.series.retain(|_, events| !events.is_empty());
+    }
+
+    /// Keeps the last `limit` events received by the graph, whatever their series.
+    fn retain_messages(&mut self, limit: usize) {
+        let mut events: Vec<_> = self.series.values().flatten().collect();
+        // an event in many series only counts once
+        events.sort_by_key(|event| Rc::as_ptr(event));
+        events.dedup_by(|a, b| Rc::ptr_eq(a, b));
+        if events.len() <= limit {
+            return;
+        }
 
-        if self.events.len() > self.limit as usize {
-            let diff = self.events.len() - self.limit as usize;
-            self.events.drain(0..diff);
+        events.sort_by_key(|event| std::cmp::Reverse(event.time));
+        let kept: HashSet<_> = events.into_iter().take(limit).map(Rc::as_ptr).collect();
+        for events in self.series.values_mut() {
+            events.retain(|event| kept.contains(&Rc::as_ptr(event)));
         }
+    }
+}
+
+/// Whether the event happened after this moment, in seconds since the Unix epoch.
+fn is_after(event: &Event, moment: f64) -> bool {
+    event.time.is_some_and(|time| time.unix_timestamp_nanos() as f64 / 1e9 > moment)
+}
+
+impl View for Flot {
+    fn title(&self) -> String {
+        DEFAULT_TITLE.to_string()
+    }
+
+    fn show(
+        &mut self,
+        ctx: &egui::Context,
+        window: egui::Window<'_>,
+        subscriptions: &mut Subscriptions,
+    ) -> Option<egui::Rect> {
+        window.show(ctx, |ui| self.ui(ui, subscriptions)).map(|inner| inner.response.rect)
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, subscriptions: &mut Subscriptions) {
+        let received = self.query.receive(subscriptions);
+        if received.resubscribed {
+            self.series.clear();
+        }
+        self.regroup();
+        for event in received.events {
+            self.insert(event);
+        }
+        self.retain(event::unix_now());
+
+        self.query.status_ui(ui);
 
-        // TODO(kerollmops) create a simple function for that
         ui.collapsing("Settings", |ui| {
             ui.group(|ui| {
                 egui::ScrollArea::vertical().show(ui, |ui| {
-                    ui.add(
-                        egui::Slider::new(&mut self.limit, 10.0..=10_000.0)
-                            .integer()
-                            .text("message limit"),
-                    );
-
-                    ui.label("query string:");
-                    let response = ui.add(
-                        egui::TextEdit::multiline(&mut self.query)
-                            .font(TextStyle::Monospace) // for cursor height
-                            .code_editor()
-                            .desired_rows(3)
-                            .lock_focus(true)
-                            .desired_width(f32::INFINITY),
-                    );
-
-                    if (response.lost_focus() && !self.query.is_empty())
-                        || self
-                            .event_receiver
-                            .as_ref()
-                            .map_or(false, |er| base_url(er.url().clone()) != base_url(url.clone()))
-                    {
-                        let wakeup = move || ();
-                        let url = websocket_url(url, true, &self.query);
-                        match EventReceiver::connect(url, wakeup) {
-                            Ok(event_receiver) => {
-                                self.events.clear();
-                                self.event_receiver = Some(event_receiver);
-                            }
-                            Err(_) => (),
+                    ui.label("title:");
+                    ui.add(egui::TextEdit::singleline(&mut self.title));
+
+                    ui.horizontal(|ui| {
+                        ui.label("keep:");
+                        ui.selectable_value(&mut self.retention, Retention::Window, "time window");
+                        ui.selectable_value(&mut self.retention, Retention::Messages, "messages");
+                    });
+                    match self.retention {
+                        Retention::Window => {
+                            ui.horizontal(|ui| {
+                                for (label, window) in
+                                    [("5m", 300.0), ("15m", 900.0), ("1h", 3600.0)]
+                                {
+                                    ui.selectable_value(&mut self.window, window, label);
+                                }
+                                ui.add(
+                                    egui::DragValue::new(&mut self.window)
+                                        .clamp_range(1.0..=86_400.0)
+                                        .suffix(" s"),
+                                );
+                            });
+                        }
+                        Retention::Messages => {
+                            ui.add(
+                                egui::Slider::new(&mut self.limit, 10.0..=10_000.0)
+                                    .integer()
+                                    .text("message limit"),
+                            );
                         }
                     }
+
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_label("series")
+                            .selected_text(self.group_by.label())
+                            .show_ui(ui, |ui| {
+                                let attribute = match &self.group_by {
+                                    GroupBy::Attribute(name) => name.clone(),
+                                    _ => String::new(),
+                                };
+                                for group_by in [
+                                    GroupBy::Host,
+                                    GroupBy::Service,
+                                    GroupBy::HostService,
+                                    GroupBy::Tag,
+                                    GroupBy::Attribute(attribute),
+                                ] {
+                                    let label = group_by.label();
+                                    ui.selectable_value(&mut self.group_by, group_by, label);
+                                }
+                            });
+                        if let GroupBy::Attribute(name) = &mut self.group_by {
+                            ui.add(egui::TextEdit::singleline(name).hint_text("name"));
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("legend:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.label)
+                                .hint_text("{host} {service}"),
+                        )
+                        .on_hover_text("{host}, {service}, {state}, {key} or any attribute name");
+                    });
+
+                    egui::ComboBox::from_label("transform")
+                        .selected_text(self.transform.label())
+                        .show_ui(ui, |ui| {
+                            for transform in Transform::ALL {
+                                let label = transform.label();
+                                ui.selectable_value(&mut self.transform, *transform, label);
+                            }
+                        });
+                    if self.transform.is_rolling() {
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::DragValue::new(&mut self.rolling_window)
+                                    .clamp_range(1.0..=86_400.0)
+                                    .prefix("over: ")
+                                    .suffix(" s"),
+                            );
+                            if self.transform == Transform::RollingPercentile {
+                                ui.add(
+                                    egui::DragValue::new(&mut self.percentile)
+                                        .clamp_range(0.0..=100.0)
+                                        .prefix("p"),
+                                );
+                            }
+                        });
+                    }
+                    ui.checkbox(&mut self.sum, "sum the series");
+
+                    egui::ComboBox::from_label("render")
+                        .selected_text(self.render.label())
+                        .show_ui(ui, |ui| {
+                            for render in Render::ALL {
+                                ui.selectable_value(&mut self.render, *render, render.label());
+                            }
+                        });
+                    ui.horizontal(|ui| {
+                        ui.label("time:");
+                        ui.selectable_value(&mut self.time_zone, TimeZone::Local, "local");
+                        ui.selectable_value(&mut self.time_zone, TimeZone::Utc, "UTC");
+                    });
+                    ui.checkbox(&mut self.follow, "follow now");
+
+                    threshold_ui(ui, "warning line", &mut self.warning, 0.0);
+                    threshold_ui(ui, "critical line", &mut self.critical, 0.0);
+                    ui.checkbox(&mut self.shade_states, "shade the non-ok states");
+
+                    self.query.settings_ui(ui, subscriptions);
+                    self.filter.settings_ui(ui, subscriptions);
                 });
             });
         });
 
-        let mut lines = BTreeMap::new(); // for color stability
-        let mut highest_x: f64 = 0.0;
-        let mut highest_y: f64 = 0.0;
-        for event in &self.events {
-            if let Some(((service, metric), time)) =
-                event.service.as_ref().zip(event.metric).zip(event.time)
-            {
-                let time = time.unix_timestamp_nanos() / 1_000_000; // millis
-                let point = Value { x: time as f64, y: metric as f64 };
-                highest_x = highest_x.max(point.x);
-                highest_y = highest_y.max(point.y);
-                lines.entry(service).or_insert_with(Vec::new).push(point);
+        if !self.title.is_empty() {
+            ui.heading(&self.title);
+        }
+
+        let now = event::unix_now() * 1000.0;
+        let mut lines = Vec::new();
+        let mut regions = Vec::new();
+        // the series are sorted by key for color stability
+        for (key, events) in &self.series {
+            if self.shade_states {
+                let events = events.iter().filter(|event| self.filter.matches(event));
+                regions.extend(state_regions(events, now));
+            }
+            let mut label = None;
+            let mut points = Vec::new();
+            for event in events.iter().filter(|event| self.filter.matches(event)) {
+                if let Some((metric, time)) = event.metric.zip(event.time) {
+                    let time = time.unix_timestamp_nanos() / 1_000_000; // millis
+                    points.push(Value { x: time as f64, y: metric as f64 });
+                    label.get_or_insert_with(|| format_label(&self.label, key, event));
+                }
+            }
+            if let Some(label) = label {
+                let window = f64::from(self.rolling_window) * 1000.0;
+                let points = self.transform.apply(&points, window, f64::from(self.percentile));
+                lines.push((label, points));
+            }
+        }
+        if self.sum && !lines.is_empty() {
+            let series: Vec<_> = lines.into_iter().map(|(_, points)| points).collect();
+            lines = vec![("sum".to_string(), sum(&series))];
+        }
+        if self.render == Render::StackedArea {
+            let series: Vec<_> = lines.iter_mut().map(|(_, points)| take(points)).collect();
+            for ((_, points), stacked) in lines.iter_mut().zip(stack(&series)) {
+                *points = stacked;
             }
         }
 
-        Plot::new("lines")
+        let highest_x = lines.iter().flat_map(|(_, points)| points).fold(0.0, |x, p| p.x.max(x));
+        let highest_y = lines.iter().flat_map(|(_, points)| points).fold(0.0, |y, p| p.y.max(y));
+        let lowest_y = lines.iter().flat_map(|(_, points)| points).fold(0.0, |y, p| p.y.min(y));
+        let thresholds = [("warning", self.warning), ("critical", self.critical)];
+        let thresholds: Vec<_> = thresholds
+            .into_iter()
+            .filter_map(|(state, threshold)| Some((state, f64::from(threshold?))))
+            .collect();
+        let highest_y = thresholds.iter().fold(highest_y, |y, (_, t)| t.max(y));
+        let lowest_y = thresholds.iter().fold(lowest_y, |y, (_, t)| t.min(y));
+
+        let offset = self.time_zone.offset();
+        let mut plot = Plot::new("lines")
             .legend(Legend::default())
             .include_x(highest_x)
             .include_y(highest_y)
-            .show(ui, |plot_ui| {
-                for (service, points) in lines {
-                    plot_ui.line(Line::new(Values::from_values(points)).name(service));
-                }
+            .include_y(lowest_y)
+            .x_axis_formatter(move |x, range| format_time(x, offset, range.end() - range.start()))
+            .label_formatter(move |name, value| {
+                let time = format_time(value.x, offset, 0.0);
+                format!("{}\n{}\n{:.2}", name, time, value.y).trim_start().to_string()
             });
+        if self.follow {
+            plot = plot.include_x(now).allow_drag(false).allow_zoom(false).allow_boxed_zoom(false);
+            if self.retention == Retention::Window {
+                plot = plot.include_x(now - f64::from(self.window) * 1000.0);
+            }
+            // scrolls even when no event is received
+            subscriptions.wakeup_after(Duration::from_secs(1));
+        }
+
+        let render = self.render;
+        plot.show(ui, |plot_ui| {
+            for (start, end, color) in regions {
+                let corners =
+                    [(start, lowest_y), (end, lowest_y), (end, highest_y), (start, highest_y)];
+                let corners = corners.into_iter().map(|(x, y)| Value { x, y }).collect();
+                let polygon = Polygon::new(Values::from_values(corners));
+                plot_ui.polygon(polygon.color(color).fill_alpha(0.15).width(0.0));
+            }
+            for (state, threshold) in thresholds {
+                let color = state_color(state).unwrap_or(Color32::GRAY);
+                let line = HLine::new(threshold).color(color).style(LineStyle::dashed_loose());
+                plot_ui.hline(line.name(state));
+            }
+            for (label, points) in lines {
+                render.draw(plot_ui, label, points);
+            }
+        });
     }
 }
 
@@ -115,9 +718,105 @@ impl Default for Flot {
     fn default() -> Self {
         Self {
             query: Default::default(),
+            filter: Default::default(),
+            title: String::new(),
+            retention: Retention::Messages,
             limit: 1000.0,
-            events: Default::default(),
-            event_receiver: Default::default(),
+            window: 300.0,
+            group_by: GroupBy::Service,
+            label: String::new(),
+            transform: Transform::Raw,
+            rolling_window: 60.0,
+            percentile: 95.0,
+            sum: false,
+            render: Render::Line,
+            time_zone: TimeZone::Local,
+            follow: false,
+            warning: None,
+            critical: None,
+            shade_states: false,
+            series: Default::default(),
+            grouped_by: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points() -> Vec<Value> {
+        [(0.0, 10.0), (1000.0, 20.0), (2000.0, 5.0), (3000.0, 8.0)]
+            .into_iter()
+            .map(|(x, y)| Value { x, y })
+            .collect()
+    }
+
+    fn event(service: &str, time: &str) -> Rc<Event> {
+        let json = serde_json::json!({ "service": service, "time": time, "tags": [] });
+        Rc::new(serde_json::from_value(json).unwrap())
+    }
+
+    #[test]
+    fn late_events_are_sorted() {
+        let mut flot = Flot::default();
+        for time in ["00:00:02", "00:00:03", "00:00:01", "00:00:04", "00:00:00"] {
+            flot.insert(event("cpu", &format!("2026-01-01T{}Z", time)));
+        }
+
+        let times: Vec<_> = flot.series["cpu"].iter().map(|e| e.time.unwrap().second()).collect();
+        assert_eq!(times, [0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn previous_dashboards_keep_their_behavior() {
+        let json = serde_json::json!({ "query": "service = \"cpu\"", "limit": 500.0 });
+        let flot: Flot = serde_json::from_value(json).unwrap();
+        assert_eq!(flot.retention, Retention::Messages);
+        assert_eq!(flot.limit, 500.0);
+        assert!(!flot.follow);
+    }
+
+    #[test]
+    fn message_limit_covers_every_series() {
+        let mut flot = Flot::default();
+        for (service, time) in [("cpu", "00"), ("memory", "01"), ("cpu", "02"), ("disk", "03")] {
+            flot.insert(event(service, &format!("2026-01-01T00:00:{}Z", time)));
         }
+        flot.limit = 2.0;
+        flot.retain(event::unix_now());
+
+        assert_eq!(flot.series.keys().collect::<Vec<_>>(), ["cpu", "disk"]);
+        assert_eq!(flot.series["cpu"][0].time.unwrap().second(), 2);
+        assert_eq!(flot.series["disk"][0].time.unwrap().second(), 3);
+    }
+
+    fn apply(transform: Transform, window: f64, percentile: f64) -> Vec<f64> {
+        transform.apply(&points(), window, percentile).iter().map(|p| p.y).collect()
+    }
+
+    #[test]
+    fn rolling() {
+        assert_eq!(apply(Transform::MovingAverage, 2000.0, 0.0), [10.0, 15.0, 12.5, 6.5]);
+        assert_eq!(apply(Transform::RollingMin, 2000.0, 0.0), [10.0, 10.0, 5.0, 5.0]);
+        assert_eq!(apply(Transform::RollingMax, 2000.0, 0.0), [10.0, 20.0, 20.0, 8.0]);
+        assert_eq!(apply(Transform::RollingPercentile, 3000.0, 0.0), [10.0, 10.0, 5.0, 5.0]);
+    }
+
+    #[test]
+    fn rates() {
+        assert_eq!(apply(Transform::Rate, 0.0, 0.0), [10.0, 5.0, 3.0]);
+        assert_eq!(apply(Transform::Derivative, 0.0, 0.0), [10.0, -15.0, 3.0]);
+    }
+
+    #[test]
+    fn out_of_range_settings() {
+        let raw = [10.0, 20.0, 5.0, 8.0];
+        assert_eq!(apply(Transform::MovingAverage, 0.0, 0.0), raw);
+        assert_eq!(apply(Transform::MovingAverage, -1000.0, 0.0), raw);
+        assert_eq!(apply(Transform::RollingPercentile, 0.0, 150.0), raw);
+        assert_eq!(apply(Transform::RollingPercentile, 3000.0, 150.0), [10.0, 20.0, 20.0, 20.0]);
+        assert_eq!(apply(Transform::RollingPercentile, 3000.0, -5.0), [10.0, 10.0, 5.0, 5.0]);
+        assert_eq!(apply(Transform::RollingPercentile, f64::NAN, f64::NAN).len(), 4);
     }
 }
@@ -0,0 +1,241 @@
+use std::f32::consts::PI;
+use std::rc::Rc;
+
+use eframe::egui;
+use eframe::egui::{vec2, Align2, Color32, Pos2, Sense, Shape, Stroke, TextStyle};
+use serde::{Deserialize, Serialize};
+
+use super::{threshold_ui, Expiry, View};
+use crate::event::{self, Event};
+use crate::query_filter::QueryFilter;
+use crate::query_subscription::QuerySubscription;
+use crate::subscriptions::Subscriptions;
+
+pub const DEFAULT_TITLE: &str = "⏲ Gauge";
+
+/// The number of segments used to draw the arcs of the dial.
+const ARC_SEGMENTS: usize = 64;
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Gauge {
+    #[serde(flatten)]
+    query: QuerySubscription,
+    filter: QueryFilter,
+    title: String,
+    style: GaugeStyle,
+    min: f32,
+    max: f32,
+    /// The gauge is colored when the metric crosses the thresholds, going from warning to
+    /// critical: a critical threshold lower than the warning one means lower is worse.
+    warning: Option<f32>,
+    critical: Option<f32>,
+    expiry: Expiry,
+    /// The latest event with a metric.
+    #[serde(skip)]
+    latest: Option<Rc<Event>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum GaugeStyle {
+    Dial,
+    Bar,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Level {
+    Ok,
+    Warning,
+    Critical,
+}
+
+impl Gauge {
+    pub fn new(title: String, query: String, max: f32) -> Self {
+        Self { title, query: QuerySubscription::new(query), max, ..Default::default() }
+    }
+
+    fn level(&self, metric: f32) -> Level {
+        // higher is worse unless the critical threshold is below the warning one
+        let lower_is_worse = matches!((self.warning, self.critical), (Some(w), Some(c)) if c < w);
+        let crossed = |threshold: Option<f32>| match threshold {
+            Some(t) if lower_is_worse => metric <= t,
+            Some(t) => metric >= t,
+            None => false,
+        };
+
+        if crossed(self.critical) {
+            Level::Critical
+        } else if crossed(self.warning) {
+            Level::Warning
+        } else {
+            Level::Ok
+        }
+    }
+
+    /// The position of the value between min and max, from 0 to 1.
+    fn fraction(&self, value: f32) -> f32 {
+        let range = self.max - self.min;
+        if range > 0.0 {
+            ((value - self.min) / range).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    fn gauge_ui(&self, ui: &mut egui::Ui, expired: bool) {
+        let current_metric = self.latest.as_ref().and_then(|event| event.metric);
+        let visuals = ui.visuals();
+        let background = visuals.faint_bg_color;
+        let text_color = if expired { visuals.weak_text_color() } else { visuals.text_color() };
+        let color = match current_metric.map(|m| self.level(m)) {
+            _ if expired => Color32::DARK_GRAY,
+            Some(Level::Ok) => Color32::GREEN,
+            Some(Level::Warning) => Color32::YELLOW,
+            Some(Level::Critical) => Color32::LIGHT_RED,
+            None => visuals.widgets.inactive.bg_fill,
+        };
+        let fraction = current_metric.map_or(0.0, |m| self.fraction(m));
+        let thresholds: Vec<_> =
+            self.warning.into_iter().chain(self.critical).map(|t| self.fraction(t)).collect();
+        let text = match current_metric {
+            Some(num) => format!("{:.02?}", num),
+            None => "-.--".to_string(),
+        };
+        let font_id = TextStyle::Heading.resolve(ui.style());
+
+        match self.style {
+            GaugeStyle::Dial => {
+                let width = ui.available_width().max(60.0);
+                let (rect, _) =
+                    ui.allocate_exact_size(vec2(width, width / 2.0 + 10.0), Sense::hover());
+                let painter = ui.painter_at(rect);
+                let center = Pos2::new(rect.center().x, rect.bottom() - 10.0);
+                let thickness = (width / 8.0).max(4.0);
+                let radius = width / 2.0 - thickness / 2.0;
+
+                // the dial goes from the left (min) to the right (max) over the top
+                let point = |fraction: f32, radius: f32| {
+                    let angle = PI * (1.0 - fraction);
+                    Pos2::new(center.x + radius * angle.cos(), center.y - radius * angle.sin())
+                };
+                let arc = |from: f32, to: f32| -> Vec<Pos2> {
+                    (0..=ARC_SEGMENTS)
+                        .map(|i| point(from + (to - from) * i as f32 / ARC_SEGMENTS as f32, radius))
+                        .collect()
+                };
+
+                painter.add(Shape::line(arc(0.0, 1.0), Stroke::new(thickness, background)));
+                if fraction > 0.0 {
+                    painter.add(Shape::line(arc(0.0, fraction), Stroke::new(thickness, color)));
+                }
+                for threshold in thresholds {
+                    let outer = point(threshold, radius + thickness / 2.0);
+                    let inner = point(threshold, radius - thickness / 2.0);
+                    painter.line_segment([outer, inner], Stroke::new(2.0, text_color));
+                }
+                painter.text(center, Align2::CENTER_BOTTOM, text, font_id, text_color);
+            }
+            GaugeStyle::Bar => {
+                let width = ui.available_width().max(60.0);
+                let (rect, _) = ui.allocate_exact_size(vec2(width, 32.0), Sense::hover());
+                let painter = ui.painter_at(rect);
+                let rounding = 4.0;
+
+                painter.rect_filled(rect, rounding, background);
+                if fraction > 0.0 {
+                    let mut filled = rect;
+                    filled.set_right(rect.left() + rect.width() * fraction);
+                    painter.rect_filled(filled, rounding, color);
+                }
+                for threshold in thresholds {
+                    let x = rect.left() + rect.width() * threshold;
+                    painter.vline(x, rect.y_range(), Stroke::new(2.0, text_color));
+                }
+                painter.text(rect.center(), Align2::CENTER_CENTER, text, font_id, text_color);
+            }
+        }
+    }
+}
+
+impl View for Gauge {
+    fn title(&self) -> String {
+        DEFAULT_TITLE.to_string()
+    }
+
+    fn show(
+        &mut self,
+        ctx: &egui::Context,
+        window: egui::Window<'_>,
+        subscriptions: &mut Subscriptions,
+    ) -> Option<egui::Rect> {
+        window.show(ctx, |ui| self.ui(ui, subscriptions)).map(|inner| inner.response.rect)
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, subscriptions: &mut Subscriptions) {
+        let received = self.query.receive(subscriptions);
+        if received.resubscribed {
+            self.latest = None;
+        }
+        for event in received.events.into_iter().filter(|event| self.filter.matches(event)) {
+            let newer = self.latest.as_ref().is_none_or(|latest| event.is_newer_than(latest));
+            if event.metric.is_some() && newer {
+                self.latest = Some(event);
+            }
+        }
+
+        let now = event::unix_now();
+        let expired = self.latest.as_ref().is_some_and(|event| event.is_expired(now));
+        if expired && self.expiry == Expiry::Remove {
+            self.latest = None;
+        }
+        self.expiry.schedule(subscriptions, now, self.latest.as_deref());
+
+        self.query.status_ui(ui);
+
+        ui.collapsing("Settings", |ui| {
+            ui.group(|ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.label("title:");
+                    ui.add(egui::TextEdit::singleline(&mut self.title));
+
+                    ui.horizontal(|ui| {
+                        ui.selectable_value(&mut self.style, GaugeStyle::Dial, "dial");
+                        ui.selectable_value(&mut self.style, GaugeStyle::Bar, "bar");
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut self.min).prefix("min: "));
+                        ui.add(egui::DragValue::new(&mut self.max).prefix("max: "));
+                    });
+
+                    threshold_ui(ui, "warning", &mut self.warning, self.max);
+                    threshold_ui(ui, "critical", &mut self.critical, self.max);
+                    self.expiry.ui(ui);
+
+                    self.query.settings_ui(ui, subscriptions);
+                    self.filter.settings_ui(ui, subscriptions);
+                });
+            });
+        });
+
+        ui.heading(&self.title);
+        self.gauge_ui(ui, expired && self.expiry == Expiry::Grey);
+    }
+}
+
+impl Default for Gauge {
+    fn default() -> Self {
+        Self {
+            query: Default::default(),
+            filter: Default::default(),
+            title: String::new(),
+            style: GaugeStyle::Dial,
+            min: 0.0,
+            max: 100.0,
+            warning: None,
+            critical: None,
+            expiry: Expiry::default(),
+            latest: None,
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use eframe::egui;
+use eframe::egui::{vec2, Align2, Color32, Sense, TextStyle};
+use serde::{Deserialize, Serialize};
+
+use super::{insert_latest, state_color, state_severity, Expiry, View};
+use crate::event::{self, Event};
+use crate::query_filter::QueryFilter;
+use crate::query_subscription::QuerySubscription;
+use crate::subscriptions::Subscriptions;
+
+pub const DEFAULT_TITLE: &str = "🔳 Grid";
+
+const CELL_SIZE: egui::Vec2 = vec2(80.0, 24.0);
+
+/// The latest event of every host and service, the hosts
+/// are the rows and the services the columns by default.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Grid {
+    #[serde(flatten)]
+    query: QuerySubscription,
+    filter: QueryFilter,
+    title: String,
+    row_sort: Sort,
+    column_sort: Sort,
+    /// Displays the services as rows and the hosts as columns.
+    swap_axes: bool,
+    expiry: Expiry,
+    #[serde(skip)]
+    events: BTreeMap<(String, String), Rc<Event>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum Sort {
+    Ascending,
+    Descending,
+    /// The rows or columns with the worst states first.
+    WorstState,
+}
+
+impl Sort {
+    const ALL: &'static [Sort] = &[Sort::Ascending, Sort::Descending, Sort::WorstState];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Sort::Ascending => "A → Z",
+            Sort::Descending => "Z → A",
+            Sort::WorstState => "worst state",
+        }
+    }
+
+    /// Sorts the names, `severity` is the worst severity of the cells of a name.
+    fn sort(&self, names: &mut [&str], severity: impl Fn(&str) -> u8) {
+        match self {
+            Sort::Ascending => names.sort_unstable(),
+            Sort::Descending => names.sort_unstable_by(|a, b| b.cmp(a)),
+            Sort::WorstState => names.sort_by_key(|name| std::cmp::Reverse(severity(name))),
+        }
+    }
+}
+
+/// The sorted rows and columns of the grid along with the events of its cells.
+struct Axes<'a> {
+    rows: Vec<&'a str>,
+    columns: Vec<&'a str>,
+    /// The events indexed by (row, column).
+    cells: BTreeMap<(&'a str, &'a str), &'a Event>,
+}
+
+impl Grid {
+    pub fn new(title: String, query: String) -> Self {
+        Self { title, query: QuerySubscription::new(query), ..Default::default() }
+    }
+
+    fn axes(&self, now: f64) -> Axes<'_> {
+        let mut cells = BTreeMap::new();
+        // the worst severity of the cells of every row and column
+        let mut rows = BTreeMap::new();
+        let mut columns = BTreeMap::new();
+        for ((host, service), event) in &self.events {
+            let (row, column) = match self.swap_axes {
+                false => (host.as_str(), service.as_str()),
+                true => (service.as_str(), host.as_str()),
+            };
+            let severity = severity(event, self.expired(event, now));
+            let row_severity = rows.entry(row).or_insert(severity);
+            *row_severity = severity.max(*row_severity);
+            let column_severity = columns.entry(column).or_insert(severity);
+            *column_severity = severity.max(*column_severity);
+            cells.insert((row, column), event.as_ref());
+        }
+
+        let mut sorted_rows: Vec<_> = rows.keys().copied().collect();
+        self.row_sort.sort(&mut sorted_rows, |row| rows[row]);
+        let mut sorted_columns: Vec<_> = columns.keys().copied().collect();
+        self.column_sort.sort(&mut sorted_columns, |column| columns[column]);
+
+        Axes { rows: sorted_rows, columns: sorted_columns, cells }
+    }
+
+    fn expired(&self, event: &Event, now: f64) -> bool {
+        self.expiry == Expiry::Grey && event.is_expired(now)
+    }
+
+    fn grid_ui(&self, ui: &mut egui::Ui, now: f64) {
+        let Axes { rows, columns, cells } = self.axes(now);
+        let font_id = TextStyle::Body.resolve(ui.style());
+
+        egui::ScrollArea::both().show(ui, |ui| {
+            egui::Grid::new("grid").spacing(vec2(2.0, 2.0)).show(ui, |ui| {
+                ui.label("");
+                for column in &columns {
+                    ui.strong(*column);
+                }
+                ui.end_row();
+
+                for row in &rows {
+                    ui.strong(*row);
+                    for column in &columns {
+                        let (rect, response) = ui.allocate_exact_size(CELL_SIZE, Sense::hover());
+                        let event = match cells.get(&(*row, *column)) {
+                            Some(event) => event,
+                            None => continue,
+                        };
+
+                        let expired = self.expired(event, now);
+                        let color = match expired {
+                            true => Color32::DARK_GRAY,
+                            false => event
+                                .state
+                                .as_deref()
+                                .and_then(state_color)
+                                .unwrap_or(Color32::GRAY),
+                        };
+                        let painter = ui.painter_at(rect);
+                        painter.rect_filled(rect, 2.0, color);
+                        if let Some(metric) = event.metric {
+                            let text = format!("{:.02?}", metric);
+                            painter.text(
+                                rect.center(),
+                                Align2::CENTER_CENTER,
+                                text,
+                                font_id.clone(),
+                                Color32::BLACK,
+                            );
+                        }
+
+                        response.on_hover_ui(|ui| {
+                            let host = event.host.as_deref().unwrap_or_default();
+                            let service = event.service.as_deref().unwrap_or_default();
+                            ui.label(format!("{} {}", host, service));
+                            if let Some(state) = &event.state {
+                                ui.label(format!("state: {}", state));
+                            }
+                            if expired {
+                                ui.label("expired");
+                            }
+                            if let Some(description) = &event.description {
+                                ui.label(description);
+                            }
+                        });
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+    }
+}
+
+/// Orders the states from the best to the worst, the expired events come first.
+fn severity(event: &Event, expired: bool) -> u8 {
+    if expired {
+        return 5;
+    }
+    state_severity(event.state.as_deref())
+}
+
+impl View for Grid {
+    fn title(&self) -> String {
+        DEFAULT_TITLE.to_string()
+    }
+
+    fn show(
+        &mut self,
+        ctx: &egui::Context,
+        window: egui::Window<'_>,
+        subscriptions: &mut Subscriptions,
+    ) -> Option<egui::Rect> {
+        window.show(ctx, |ui| self.ui(ui, subscriptions)).map(|inner| inner.response.rect)
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, subscriptions: &mut Subscriptions) {
+        let received = self.query.receive(subscriptions);
+        if received.resubscribed {
+            self.events.clear();
+        }
+        for event in received.events.into_iter().filter(|event| self.filter.matches(event)) {
+            let host = event.host.clone().unwrap_or_default();
+            let service = event.service.clone().unwrap_or_default();
+            insert_latest(&mut self.events, (host, service), event);
+        }
+
+        let now = event::unix_now();
+        if self.expiry == Expiry::Remove {
+            self.events.retain(|_, event| !event.is_expired(now));
+        }
+        self.expiry.schedule(subscriptions, now, self.events.values().map(Rc::as_ref));
+
+        self.query.status_ui(ui);
+
+        ui.collapsing("Settings", |ui| {
+            ui.group(|ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.label("title:");
+                    ui.add(egui::TextEdit::singleline(&mut self.title));
+
+                    sort_ui(ui, "sort rows", &mut self.row_sort);
+                    sort_ui(ui, "sort columns", &mut self.column_sort);
+                    ui.checkbox(&mut self.swap_axes, "services as rows");
+                    self.expiry.ui(ui);
+
+                    self.query.settings_ui(ui, subscriptions);
+                    self.filter.settings_ui(ui, subscriptions);
+                });
+            });
+        });
+
+        ui.heading(&self.title);
+        self.grid_ui(ui, now);
+    }
+}
+
+fn sort_ui(ui: &mut egui::Ui, label: &str, sort: &mut Sort) {
+    egui::ComboBox::from_label(label).selected_text(sort.label()).show_ui(ui, |ui| {
+        for s in Sort::ALL {
+            ui.selectable_value(sort, *s, s.label());
+        }
+    });
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Self {
+            query: Default::default(),
+            filter: Default::default(),
+            title: String::new(),
+            row_sort: Sort::Ascending,
+            column_sort: Sort::Ascending,
+            swap_axes: false,
+            expiry: Expiry::default(),
+            events: Default::default(),
+        }
+    }
+}
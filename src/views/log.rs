@@ -1,20 +1,35 @@
+use std::rc::Rc;
+
 use eframe::egui;
-use eframe::egui::{Color32, RichText, TextStyle};
+use eframe::egui::{Color32, RichText};
 use egui_extras::{Size, TableBuilder};
-use ewebsock::{WsEvent, WsMessage};
-use url::Url;
+use serde::{Deserialize, Serialize};
 
-use super::View;
-use crate::event::{Event, EventReceiver};
-use crate::{base_url, websocket_url};
+use super::{insert_by_time, View};
+use crate::event::Event;
+use crate::query_filter::QueryFilter;
+use crate::query_subscription::QuerySubscription;
+use crate::subscriptions::Subscriptions;
 
 pub const DEFAULT_TITLE: &str = "📃 Scrolling List";
 
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct Log {
-    query: String,
+    #[serde(flatten)]
+    query: QuerySubscription,
+    filter: QueryFilter,
+    title: String,
     limit: f32,
-    events: Vec<Event>,
-    event_receiver: Option<EventReceiver>,
+    /// The received events, oldest first.
+    #[serde(skip)]
+    events: Vec<Rc<Event>>,
+}
+
+impl Log {
+    pub fn new(title: String, query: String, limit: f32) -> Self {
+        Self { title, query: QuerySubscription::new(query), limit, ..Default::default() }
+    }
 }
 
 impl View for Log {
@@ -22,19 +37,22 @@ impl View for Log {
         DEFAULT_TITLE.to_string()
     }
 
-    fn show(&mut self, ctx: &egui::Context, id: egui::Id, url: &Url, open: &mut bool) {
-        egui::Window::new(self.title()).id(id).open(open).show(ctx, |ui| self.ui(ui, url));
+    fn show(
+        &mut self,
+        ctx: &egui::Context,
+        window: egui::Window<'_>,
+        subscriptions: &mut Subscriptions,
+    ) -> Option<egui::Rect> {
+        window.show(ctx, |ui| self.ui(ui, subscriptions)).map(|inner| inner.response.rect)
     }
 
-    fn ui(&mut self, ui: &mut egui::Ui, url: &Url) {
-        if let Some(event_receiver) = &self.event_receiver {
-            while let Some(event) = event_receiver.try_recv() {
-                if let WsEvent::Message(WsMessage::Text(text)) = event {
-                    if let Ok(event) = serde_json::from_str::<Event>(&text) {
-                        self.events.push(event);
-                    }
-                }
-            }
+    fn ui(&mut self, ui: &mut egui::Ui, subscriptions: &mut Subscriptions) {
+        let received = self.query.receive(subscriptions);
+        if received.resubscribed {
+            self.events.clear();
+        }
+        for event in received.events {
+            insert_by_time(&mut self.events, event);
         }
 
         if self.events.len() > self.limit as usize {
@@ -42,46 +60,33 @@ impl View for Log {
             self.events.drain(0..diff);
         }
 
-        // TODO(kerollmops) create a simple function for that
+        self.query.status_ui(ui);
+
         ui.collapsing("Settings", |ui| {
             ui.group(|ui| {
                 egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.label("title:");
+                    ui.add(egui::TextEdit::singleline(&mut self.title));
+
                     ui.add(
                         egui::Slider::new(&mut self.limit, 10.0..=10_000.0)
                             .integer()
                             .text("message limit"),
                     );
 
-                    ui.label("query string:");
-                    let response = ui.add(
-                        egui::TextEdit::multiline(&mut self.query)
-                            .font(TextStyle::Monospace) // for cursor height
-                            .code_editor()
-                            .desired_rows(3)
-                            .lock_focus(true)
-                            .desired_width(f32::INFINITY),
-                    );
-
-                    if (response.lost_focus() && !self.query.is_empty())
-                        || self
-                            .event_receiver
-                            .as_ref()
-                            .map_or(false, |er| base_url(er.url().clone()) != base_url(url.clone()))
-                    {
-                        let wakeup = move || ();
-                        let url = websocket_url(url, true, &self.query);
-                        match EventReceiver::connect(url, wakeup) {
-                            Ok(event_receiver) => {
-                                self.events.clear();
-                                self.event_receiver = Some(event_receiver);
-                            }
-                            Err(_) => (),
-                        }
-                    }
+                    self.query.settings_ui(ui, subscriptions);
+                    self.filter.settings_ui(ui, subscriptions);
                 });
             });
         });
 
+        if !self.title.is_empty() {
+            ui.heading(&self.title);
+        }
+
+        let filter = &mut self.filter;
+        let events: Vec<_> = self.events.iter().filter(|event| filter.matches(event)).collect();
+
         egui::ScrollArea::vertical().stick_to_bottom().show(ui, |ui| {
             TableBuilder::new(ui)
                 .resizable(true)
@@ -109,7 +114,7 @@ impl View for Log {
                     });
                 })
                 .body(|mut body| {
-                    for event in &self.events {
+                    for event in events {
                         body.row(25.0, |mut row| {
                             row.col(|ui| {
                                 if let Some(host) = &event.host {
@@ -151,9 +156,10 @@ impl Default for Log {
     fn default() -> Self {
         Self {
             query: Default::default(),
+            filter: Default::default(),
+            title: String::new(),
             limit: 1000.0,
             events: Default::default(),
-            event_receiver: Default::default(),
         }
     }
 }
@@ -1,13 +1,215 @@
+use std::collections::btree_map::{BTreeMap, Entry};
+use std::rc::Rc;
+use std::time::Duration;
+
 use eframe::egui;
-use url::Url;
+use eframe::egui::Color32;
+use serde::{Deserialize, Serialize};
+
+use crate::event::Event;
+use crate::subscriptions::Subscriptions;
 
 pub mod about;
 pub mod big_number;
 pub mod flot;
+pub mod gauge;
+pub mod grid;
 pub mod log;
 
 pub trait View {
     fn title(&self) -> String;
-    fn show(&mut self, ctx: &egui::Context, id: egui::Id, url: &Url, open: &mut bool);
-    fn ui(&mut self, ui: &mut egui::Ui, url: &Url);
+    /// Shows the view in the given window and returns the area it covers, if visible.
+    fn show(
+        &mut self,
+        ctx: &egui::Context,
+        window: egui::Window<'_>,
+        subscriptions: &mut Subscriptions,
+    ) -> Option<egui::Rect>;
+    fn ui(&mut self, ui: &mut egui::Ui, subscriptions: &mut Subscriptions);
+}
+
+/// The color of the well-known Riemann states.
+pub fn state_color(state: &str) -> Option<Color32> {
+    match state {
+        "ok" => Some(Color32::from_rgb(0x5c, 0xb8, 0x5c)),
+        "warning" => Some(Color32::from_rgb(0xf0, 0xad, 0x4e)),
+        "critical" => Some(Color32::from_rgb(0xd9, 0x53, 0x4f)),
+        _ => None,
+    }
+}
+
+/// Orders the states from the best to the worst.
+pub fn state_severity(state: Option<&str>) -> u8 {
+    match state {
+        Some("ok") => 0,
+        None => 1,
+        Some("warning") => 3,
+        Some("critical") => 4,
+        Some(_) => 2,
+    }
+}
+
+/// Inserts the event unless an event newer than it is already there, returns `true` if inserted.
+pub fn insert_latest<K: Ord>(
+    events: &mut BTreeMap<K, Rc<Event>>,
+    key: K,
+    event: Rc<Event>,
+) -> bool {
+    match events.entry(key) {
+        Entry::Occupied(mut entry) if event.is_newer_than(entry.get()) => {
+            entry.insert(event);
+            true
+        }
+        Entry::Occupied(_) => false,
+        Entry::Vacant(entry) => {
+            entry.insert(event);
+            true
+        }
+    }
+}
+
+/// Inserts the event after the events that happened before it, the events
+/// of the index may arrive after newer live ones.
+pub fn insert_by_time(events: &mut Vec<Rc<Event>>, event: Rc<Event>) {
+    let position = events.iter().rposition(|e| e.time <= event.time).map_or(0, |i| i + 1);
+    events.insert(position, event);
+}
+
+/// An optional threshold, enabled with the default value.
+pub fn threshold_ui(ui: &mut egui::Ui, name: &str, threshold: &mut Option<f32>, default: f32) {
+    ui.horizontal(|ui| {
+        let mut enabled = threshold.is_some();
+        if ui.checkbox(&mut enabled, name).changed() {
+            *threshold = if enabled { Some(default) } else { None };
+        }
+        if let Some(threshold) = threshold {
+            ui.add(egui::DragValue::new(threshold));
+        }
+    });
+}
+
+/// What the views displaying the latest state do with the events whose TTL elapsed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Expiry {
+    Ignore,
+    #[default]
+    Grey,
+    Remove,
+}
+
+impl Expiry {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("expired events:");
+            ui.selectable_value(self, Expiry::Ignore, "keep");
+            ui.selectable_value(self, Expiry::Grey, "grey");
+            ui.selectable_value(self, Expiry::Remove, "remove");
+        });
+    }
+
+    /// Wakes the dashboard up when the next of these events expires.
+    pub fn schedule<'a>(
+        &self,
+        subscriptions: &mut Subscriptions,
+        now: f64,
+        events: impl IntoIterator<Item = &'a Event>,
+    ) {
+        if *self == Expiry::Ignore {
+            return;
+        }
+
+        let next = events
+            .into_iter()
+            .filter_map(Event::expires_at)
+            .filter(|expires_at| *expires_at > now)
+            .min_by(f64::total_cmp);
+        if let Some(next) = next {
+            subscriptions.wakeup_after(Duration::from_secs_f64(next - now));
+        }
+    }
+}
+
+/// Every kind of view that can be stored in a workspace.
+///
+/// Trait objects can't be (de)serialized, this enum is what
+/// makes the views of a workspace persist across restarts.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum AnyView {
+    Log(log::Log),
+    Flot(flot::Flot),
+    BigNumber(big_number::BigNumber),
+    Gauge(gauge::Gauge),
+    Grid(grid::Grid),
+}
+
+impl AnyView {
+    fn as_view_mut(&mut self) -> &mut dyn View {
+        match self {
+            AnyView::Log(view) => view,
+            AnyView::Flot(view) => view,
+            AnyView::BigNumber(view) => view,
+            AnyView::Gauge(view) => view,
+            AnyView::Grid(view) => view,
+        }
+    }
+}
+
+impl View for AnyView {
+    fn title(&self) -> String {
+        match self {
+            AnyView::Log(view) => view.title(),
+            AnyView::Flot(view) => view.title(),
+            AnyView::BigNumber(view) => view.title(),
+            AnyView::Gauge(view) => view.title(),
+            AnyView::Grid(view) => view.title(),
+        }
+    }
+
+    fn show(
+        &mut self,
+        ctx: &egui::Context,
+        window: egui::Window<'_>,
+        subscriptions: &mut Subscriptions,
+    ) -> Option<egui::Rect> {
+        self.as_view_mut().show(ctx, window, subscriptions)
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, subscriptions: &mut Subscriptions) {
+        self.as_view_mut().ui(ui, subscriptions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(time: &str, metric: f32) -> Rc<Event> {
+        let json = serde_json::json!({ "time": time, "metric": metric, "tags": [] });
+        Rc::new(serde_json::from_value(json).unwrap())
+    }
+
+    #[test]
+    fn older_events_are_ignored() {
+        let mut events = BTreeMap::new();
+        assert!(insert_latest(&mut events, "cpu", event("2026-01-01T00:00:02Z", 2.0)));
+        // an event of the index arriving after the live one
+        assert!(!insert_latest(&mut events, "cpu", event("2026-01-01T00:00:01Z", 1.0)));
+        assert_eq!(events["cpu"].metric, Some(2.0));
+        assert!(insert_latest(&mut events, "cpu", event("2026-01-01T00:00:03Z", 3.0)));
+        assert_eq!(events["cpu"].metric, Some(3.0));
+    }
+
+    #[test]
+    fn late_events_are_sorted_by_time() {
+        let mut events = Vec::new();
+        insert_by_time(&mut events, event("2026-01-01T00:00:02Z", 2.0));
+        insert_by_time(&mut events, event("2026-01-01T00:00:03Z", 3.0));
+        // the snapshot of the index arriving after the live events
+        insert_by_time(&mut events, event("2026-01-01T00:00:01Z", 1.0));
+        insert_by_time(&mut events, event("2026-01-01T00:00:03Z", 4.0));
+
+        let metrics: Vec<_> = events.iter().map(|e| e.metric).collect();
+        assert_eq!(metrics, [Some(1.0), Some(2.0), Some(3.0), Some(4.0)]);
+    }
 }
@@ -1,95 +1,289 @@
 use eframe::egui;
 use eframe::egui::containers::panel::Side;
-use eframe::egui::{Color32, Id, TextEdit};
-use url::Url;
+use eframe::egui::{Color32, Id, Rect, RichText, TextEdit};
+use serde::{Deserialize, Serialize};
 
 use crate::confirm_button::{Clicked, ConfirmButton};
+use crate::subscriptions::Subscriptions;
 use crate::views::*;
 
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct Workspace {
     pub name: String,
+    #[serde(skip)]
     delete_clicked: Clicked,
     about: Option<about::About>,
-    views: Vec<Box<dyn View>>,
+    views: Vec<AnyView>,
+    /// A stable identifier for each view, its window keeps its place when an earlier view is deleted.
+    ids: Vec<usize>,
+    /// The area covered by each view window during the last frame.
+    #[serde(skip)]
+    rects: Vec<Option<Rect>>,
+    /// Whether the windows must be moved to the `rects` positions, after an import.
+    #[serde(skip)]
+    move_windows: bool,
+    #[serde(skip)]
+    transfer: Transfer,
+}
+
+/// The state of the export and import section of the side panel.
+#[derive(Default)]
+struct Transfer {
+    /// The file path on native, the pasted JSON document on the web.
+    input: String,
+    error: Option<String>,
+    /// The last imported workspace, the app adds it next to this one.
+    imported: Option<Box<Workspace>>,
+}
+
+/// The version of the JSON document produced by [`Workspace::to_json`].
+pub const DOCUMENT_VERSION: u32 = 1;
+
+/// A workspace as it is exported, the views are flattened with their window area.
+#[derive(Serialize, Deserialize)]
+struct WorkspaceDocument<V> {
+    version: u32,
+    name: String,
+    views: Vec<ViewDocument<V>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ViewDocument<V> {
+    #[serde(flatten)]
+    view: V,
+    #[serde(default)]
+    rect: Option<Rect>,
 }
 
 impl Workspace {
-    pub fn ui(&mut self, parent_id: Id, url: &Url, open: &mut bool, ctx: &egui::Context) {
-        egui::SidePanel::new(Side::Right, parent_id.with("workspace_right_panel"))
-            .resizable(false)
-            .show(ctx, |ui| {
-                ui.add_space(5.);
+    pub fn new(name: String, about: bool, views: Vec<AnyView>) -> Workspace {
+        Workspace { name, about: about.then_some(about::About), views, ..Default::default() }
+    }
 
-                ui.add(TextEdit::singleline(&mut self.name).hint_text("Workspace Name"));
+    pub fn ui(
+        &mut self,
+        parent_id: Id,
+        subscriptions: &mut Subscriptions,
+        kiosk: bool,
+        open: &mut bool,
+        ctx: &egui::Context,
+    ) {
+        if !kiosk {
+            egui::SidePanel::new(Side::Right, parent_id.with("workspace_right_panel"))
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.add_space(5.);
 
-                ui.separator();
+                    ui.add(TextEdit::singleline(&mut self.name).hint_text("Workspace Name"));
 
-                if ui.selectable_label(self.about.is_some(), about::DEFAULT_TITLE).clicked() {
-                    self.about = match self.about {
-                        Some(_) => None,
-                        None => Some(about::About::default()),
-                    };
-                }
+                    ui.separator();
 
-                ui.separator();
+                    if ui.selectable_label(self.about.is_some(), about::DEFAULT_TITLE).clicked() {
+                        self.about = match self.about {
+                            Some(_) => None,
+                            None => Some(about::About),
+                        };
+                    }
 
-                if ui.button(log::DEFAULT_TITLE).clicked() {
-                    self.views.push(Box::new(log::Log::default()));
-                }
-                if ui.button(flot::DEFAULT_TITLE).clicked() {
-                    self.views.push(Box::new(flot::Flot::default()));
-                }
-                if ui.button(big_number::DEFAULT_TITLE).clicked() {
-                    self.views.push(Box::new(big_number::BigNumber::default()));
-                }
+                    ui.separator();
 
-                ui.separator();
+                    if ui.button(log::DEFAULT_TITLE).clicked() {
+                        self.push_view(AnyView::Log(log::Log::default()));
+                    }
+                    if ui.button(flot::DEFAULT_TITLE).clicked() {
+                        self.push_view(AnyView::Flot(flot::Flot::default()));
+                    }
+                    if ui.button(big_number::DEFAULT_TITLE).clicked() {
+                        self.push_view(AnyView::BigNumber(big_number::BigNumber::default()));
+                    }
+                    if ui.button(gauge::DEFAULT_TITLE).clicked() {
+                        self.push_view(AnyView::Gauge(gauge::Gauge::default()));
+                    }
+                    if ui.button(grid::DEFAULT_TITLE).clicked() {
+                        self.push_view(AnyView::Grid(grid::Grid::default()));
+                    }
 
-                ui.add(
-                    ConfirmButton::new(
-                        &mut self.delete_clicked,
-                        "🗑 Delete workpace",
-                        "🗑 Click again",
-                    )
-                    .fill(Color32::LIGHT_RED),
-                );
+                    ui.separator();
 
-                if self.delete_clicked.confirm_clicked() {
-                    *open = false;
-                }
+                    ui.add(
+                        ConfirmButton::new(
+                            &mut self.delete_clicked,
+                            "🗑 Delete workpace",
+                            "🗑 Click again",
+                        )
+                        .fill(Color32::LIGHT_RED),
+                    );
 
-                if ui.button("Organize windows").clicked() {
-                    ui.ctx().memory().reset_areas();
-                    ui.close_menu();
-                }
-            });
+                    if self.delete_clicked.confirm_clicked() {
+                        *open = false;
+                    }
+
+                    if ui.button("Organize windows").clicked() {
+                        ui.ctx().memory().reset_areas();
+                        ui.close_menu();
+                    }
+
+                    ui.separator();
+
+                    self.transfer_ui(ui);
+                });
+        }
 
         let mut to_delete = Vec::new();
         egui::CentralPanel::default().show(ctx, |_ui| {
             let mut open = true;
             if let Some(about) = self.about.as_mut() {
-                about.show(ctx, parent_id.with("about"), url, &mut open);
-                if open == false {
+                let window = egui::Window::new(about.title()).id(parent_id.with("about"));
+                about.show(ctx, window.open(&mut open), subscriptions);
+                if !open {
                     self.about = None;
                 }
             }
 
-            for (i, view) in self.views.iter_mut().enumerate() {
+            self.assign_ids();
+            self.rects.resize(self.views.len(), None);
+            let windows = self.views.iter_mut().zip(&self.ids).zip(&mut self.rects).enumerate();
+            for (i, ((view, id), rect)) in windows {
                 let mut open = true;
-                view.show(ctx, parent_id.with(i), url, &mut open);
-                if open == false {
+                let mut window = egui::Window::new(view.title()).id(parent_id.with(id));
+                if let Some(rect) = rect.filter(|_| self.move_windows) {
+                    window = window.current_pos(rect.min).default_size(rect.size());
+                }
+                *rect = view.show(ctx, window.open(&mut open), subscriptions);
+                if !open {
                     to_delete.push(i);
                 }
             }
+            self.move_windows = false;
         });
 
-        let mut removed = 0;
-        for i in to_delete {
-            self.views.remove(i - removed);
-            removed += 1;
+        self.remove_views(to_delete);
+    }
+
+    fn push_view(&mut self, view: AnyView) {
+        self.assign_ids();
+        self.ids.push(self.ids.iter().max().map_or(0, |id| id + 1));
+        self.views.push(view);
+    }
+
+    /// Gives an identifier to the views that don't have one yet, the views restored
+    /// from a previous version get their index, the identifier their window had back then.
+    fn assign_ids(&mut self) {
+        self.ids.truncate(self.views.len());
+        while self.ids.len() < self.views.len() {
+            let index = self.ids.len();
+            let id = if self.ids.contains(&index) {
+                self.ids.iter().max().map_or(0, |id| id + 1)
+            } else {
+                index
+            };
+            self.ids.push(id);
         }
     }
 
+    /// Removes the views at the given ascending indices, with their identifier and area.
+    fn remove_views(&mut self, indices: Vec<usize>) {
+        self.assign_ids();
+        self.rects.resize(self.views.len(), None);
+        for i in indices.into_iter().rev() {
+            self.views.remove(i);
+            self.ids.remove(i);
+            self.rects.remove(i);
+        }
+    }
+
+    /// Serializes the name, the views and the layout of this workspace into a JSON document.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        let views = self
+            .views
+            .iter()
+            .zip(self.rects.iter().chain(std::iter::repeat(&None)))
+            .map(|(view, rect)| ViewDocument { view, rect: *rect })
+            .collect();
+        let document =
+            WorkspaceDocument { version: DOCUMENT_VERSION, name: self.name.clone(), views };
+        serde_json::to_string_pretty(&document)
+    }
+
+    /// Deserializes a workspace from a JSON document generated by [`Workspace::to_json`].
+    pub fn from_json(json: &str) -> anyhow::Result<Workspace> {
+        let document: WorkspaceDocument<AnyView> = serde_json::from_str(json)?;
+        if document.version > DOCUMENT_VERSION {
+            anyhow::bail!("unsupported workspace document version {}", document.version);
+        }
+
+        let (views, rects) = document.views.into_iter().map(|doc| (doc.view, doc.rect)).unzip();
+        Ok(Workspace {
+            name: document.name,
+            about: None,
+            views,
+            rects,
+            move_windows: true,
+            ..Default::default()
+        })
+    }
+
+    fn transfer_ui(&mut self, ui: &mut egui::Ui) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            ui.add(TextEdit::singleline(&mut self.transfer.input).hint_text("dashboard.json"));
+            ui.horizontal(|ui| {
+                if ui.button("Export workspace").clicked() {
+                    let result = self.to_json().map_err(Into::into).and_then(|json| {
+                        std::fs::write(&self.transfer.input, json).map_err(anyhow::Error::from)
+                    });
+                    self.transfer.error = result.err().map(|e| e.to_string());
+                }
+                if ui.button("Import workspace").clicked() {
+                    let result = std::fs::read_to_string(&self.transfer.input)
+                        .map_err(anyhow::Error::from)
+                        .and_then(|json| Workspace::from_json(&json));
+                    self.import(result);
+                }
+            });
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            if ui.button("Export workspace").on_hover_text("Copy to the clipboard").clicked() {
+                match self.to_json() {
+                    Ok(json) => ui.output().copied_text = json,
+                    Err(e) => self.transfer.error = Some(e.to_string()),
+                }
+            }
+            ui.add(
+                TextEdit::multiline(&mut self.transfer.input)
+                    .hint_text("paste a workspace document")
+                    .desired_rows(2),
+            );
+            if ui.button("Import workspace").clicked() {
+                let result = Workspace::from_json(&self.transfer.input);
+                self.import(result);
+            }
+        }
+
+        if let Some(error) = &self.transfer.error {
+            ui.label(RichText::new(error).color(Color32::LIGHT_RED));
+        }
+    }
+
+    /// Keeps the imported workspace until the app takes it, the current one is left untouched.
+    fn import(&mut self, result: anyhow::Result<Workspace>) {
+        match result {
+            Ok(workspace) => {
+                self.transfer.imported = Some(Box::new(workspace));
+                self.transfer.error = None;
+            }
+            Err(e) => self.transfer.error = Some(e.to_string()),
+        }
+    }
+
+    /// The workspace imported from this one during the last frame, if any.
+    pub fn take_imported(&mut self) -> Option<Workspace> {
+        self.transfer.imported.take().map(|workspace| *workspace)
+    }
+
     pub fn reset_confirm_delete(&mut self) {
         self.delete_clicked = Clicked::default();
     }
@@ -100,8 +294,42 @@ impl Default for Workspace {
         Self {
             name: "Riemann".to_string(),
             delete_clicked: Default::default(),
-            about: Some(about::About::default()),
+            about: Some(about::About),
             views: Default::default(),
+            ids: Default::default(),
+            rects: Default::default(),
+            move_windows: false,
+            transfer: Default::default(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn view_ids_survive_a_deletion() {
+        let mut workspace = Workspace::new("test".to_string(), false, Vec::new());
+        for _ in 0..3 {
+            workspace.push_view(AnyView::Log(log::Log::default()));
+        }
+        assert_eq!(workspace.ids, [0, 1, 2]);
+
+        workspace.remove_views(vec![0]);
+        let json = serde_json::to_string(&workspace).unwrap();
+        let mut restored: Workspace = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.ids, [1, 2]);
+
+        restored.push_view(AnyView::Log(log::Log::default()));
+        assert_eq!(restored.ids, [1, 2, 3]);
+    }
+
+    #[test]
+    fn views_without_ids_keep_their_index() {
+        let views = (0..2).map(|_| AnyView::Log(log::Log::default())).collect();
+        let mut workspace = Workspace::new("test".to_string(), false, views);
+        workspace.assign_ids();
+        assert_eq!(workspace.ids, [0, 1]);
+    }
+}